@@ -0,0 +1,313 @@
+//! API-key and IP allowlist gate built on `tower::filter`.
+//!
+//! A request passes when its path is exempt, or when its peer address is in
+//! the allowlist (if one is configured) and it carries a known key in
+//! `x-api-key` or `Authorization: Bearer` (if a key file is configured).
+//! Rejections surface as [`AccessDenied`] errors that
+//! [`handle_rejection`] turns into JSON 401/403 responses.
+//!
+//! Configured from the environment:
+//! - `ACCESS_KEYS_FILE`: one key per line, `#` starts a comment. The file is
+//!   re-read when its modification time changes.
+//! - `ACCESS_ALLOW`: comma-separated CIDRs such as `10.0.0.0/8,::1/128`.
+//! - `ACCESS_EXEMPT`: comma-separated paths; a trailing `/*` exempts a whole
+//!   subtree. Defaults to `/healthz` and `/readyz`.
+
+use std::{
+    collections::HashSet,
+    fmt, fs, io,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    BoxError,
+};
+use server_core::{error::ErrorResponse, shutdown::Shutdown};
+use tower::filter::Predicate;
+
+/// An IPv4 or IPv6 network such as `192.168.0.0/16`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        // Clients reaching an IPv6 socket over IPv4 show up as ::ffff:a.b.c.d.
+        match (self.network, addr.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let network: IpAddr = addr
+            .parse()
+            .map_err(|_| format!("`{addr}` is not an IP address"))?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|&p| p <= max)
+                .ok_or_else(|| format!("prefix length `{prefix}` must be between 0 and {max}"))?,
+            None => max,
+        };
+        Ok(Cidr { network, prefix })
+    }
+}
+
+/// API keys loaded from a file, shared between the filter and the reloader.
+#[derive(Clone)]
+pub struct KeyStore {
+    path: PathBuf,
+    keys: Arc<RwLock<HashSet<String>>>,
+    modified: Arc<RwLock<Option<SystemTime>>>,
+}
+
+impl KeyStore {
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let store = KeyStore {
+            path: path.into(),
+            keys: Arc::default(),
+            modified: Arc::default(),
+        };
+        store.reload()?;
+        Ok(store)
+    }
+
+    /// Re-read the key file, replacing every key at once.
+    pub fn reload(&self) -> io::Result<usize> {
+        let modified = fs::metadata(&self.path)?.modified().ok();
+        let keys: HashSet<String> = fs::read_to_string(&self.path)?
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or("").trim())
+            .filter(|key| !key.is_empty())
+            .map(String::from)
+            .collect();
+        let count = keys.len();
+        *self.keys.write().unwrap() = keys;
+        *self.modified.write().unwrap() = modified;
+        Ok(count)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.keys.read().unwrap().contains(key)
+    }
+
+    /// Reload the file whenever its modification time changes, until
+    /// shutdown. A file that fails to read keeps the previous keys.
+    pub fn spawn_reloader(
+        &self,
+        every: Duration,
+        shutdown: &Shutdown,
+    ) -> tokio::task::JoinHandle<()> {
+        let store = self.clone();
+        let token = shutdown.token();
+        shutdown.spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    () = token.cancelled() => break,
+                }
+                let modified = fs::metadata(&store.path).and_then(|m| m.modified()).ok();
+                if modified == *store.modified.read().unwrap() {
+                    continue;
+                }
+                match store.reload() {
+                    Ok(count) => {
+                        tracing::info!(count, path = %store.path.display(), "reloaded API keys")
+                    }
+                    Err(err) => {
+                        tracing::warn!(%err, path = %store.path.display(), "failed to reload API keys")
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Who may call the service. Used as a `tower::filter` predicate.
+#[derive(Clone, Default)]
+pub struct AccessPolicy {
+    keys: Option<KeyStore>,
+    allow: Vec<Cidr>,
+    exempt: Vec<String>,
+}
+
+impl AccessPolicy {
+    pub fn new(keys: Option<KeyStore>, allow: Vec<Cidr>, exempt: Vec<String>) -> Self {
+        AccessPolicy {
+            keys,
+            allow,
+            exempt,
+        }
+    }
+
+    /// Build the policy from `ACCESS_KEYS_FILE`, `ACCESS_ALLOW` and
+    /// `ACCESS_EXEMPT`.
+    pub fn from_env() -> Result<Self, String> {
+        let keys = match std::env::var_os("ACCESS_KEYS_FILE") {
+            Some(path) => Some(
+                KeyStore::load(&path)
+                    .map_err(|err| format!("cannot read {}: {err}", path.to_string_lossy()))?,
+            ),
+            None => None,
+        };
+        let allow = env_list("ACCESS_ALLOW")
+            .iter()
+            .map(|s| s.parse())
+            .collect::<Result<_, _>>()?;
+        let mut exempt = env_list("ACCESS_EXEMPT");
+        if std::env::var_os("ACCESS_EXEMPT").is_none() {
+            exempt.extend(["/healthz".to_string(), "/readyz".to_string()]);
+        }
+        Ok(AccessPolicy::new(keys, allow, exempt))
+    }
+
+    pub fn keys(&self) -> Option<&KeyStore> {
+        self.keys.as_ref()
+    }
+
+    /// Whether the policy restricts anything at all.
+    pub fn is_enabled(&self) -> bool {
+        self.keys.is_some() || !self.allow.is_empty()
+    }
+
+    fn is_exempt(&self, path: &str) -> bool {
+        self.exempt
+            .iter()
+            .any(|pattern| match pattern.strip_suffix("/*") {
+                Some(prefix) => path == prefix || path.starts_with(&format!("{prefix}/")),
+                None => path == pattern,
+            })
+    }
+
+    fn authorize(&self, request: &Request) -> Result<(), AccessDenied> {
+        if self.is_exempt(request.uri().path()) {
+            return Ok(());
+        }
+        if !self.allow.is_empty() {
+            let peer = request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip());
+            if !peer.is_some_and(|ip| self.allow.iter().any(|cidr| cidr.contains(ip))) {
+                return Err(AccessDenied::Forbidden);
+            }
+        }
+        if let Some(keys) = &self.keys {
+            match presented_key(request) {
+                Some(key) if keys.contains(key) => {}
+                Some(_) => return Err(AccessDenied::InvalidKey),
+                None => return Err(AccessDenied::MissingKey),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Predicate<Request> for AccessPolicy {
+    type Request = Request;
+
+    fn check(&mut self, request: Request) -> Result<Request, BoxError> {
+        self.authorize(&request)?;
+        Ok(request)
+    }
+}
+
+fn env_list(name: &str) -> Vec<String> {
+    std::env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn presented_key(request: &Request) -> Option<&str> {
+    let headers = request.headers();
+    if let Some(key) = headers.get("x-api-key") {
+        return key.to_str().ok();
+    }
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+#[derive(Debug)]
+pub enum AccessDenied {
+    MissingKey,
+    InvalidKey,
+    Forbidden,
+}
+
+impl fmt::Display for AccessDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AccessDenied::MissingKey => "an API key is required",
+            AccessDenied::InvalidKey => "the API key is not valid",
+            AccessDenied::Forbidden => "your address is not allowed to use this service",
+        })
+    }
+}
+
+impl std::error::Error for AccessDenied {}
+
+/// Error handler for `HandleErrorLayer` in front of the filter.
+pub async fn handle_rejection(err: BoxError) -> Response {
+    let Some(denied) = err.downcast_ref::<AccessDenied>() else {
+        tracing::error!(%err, "unhandled service error");
+        return ErrorResponse::internal().into_response();
+    };
+    let (status, error) = match denied {
+        AccessDenied::MissingKey | AccessDenied::InvalidKey => {
+            (StatusCode::UNAUTHORIZED, "unauthorized")
+        }
+        AccessDenied::Forbidden => (StatusCode::FORBIDDEN, "forbidden"),
+    };
+    let mut response = ErrorResponse::new(status, error, denied.to_string()).into_response();
+    if status == StatusCode::UNAUTHORIZED {
+        response.headers_mut().insert(
+            header::WWW_AUTHENTICATE,
+            header::HeaderValue::from_static("Bearer"),
+        );
+    }
+    response
+}
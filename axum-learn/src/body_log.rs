@@ -0,0 +1,345 @@
+//! Middleware that logs request and response bodies at `DEBUG` level.
+//!
+//! Enable it with `RUST_LOG=axum_learn::body_log=debug`. Only textual bodies
+//! with a known length up to `max_bytes` are buffered; streams (no length,
+//! `text/event-stream`, NDJSON) and binary content are passed through
+//! untouched with only a note (and the size, when known) in the log. Values
+//! of JSON fields named in `redact` are replaced before logging, at any
+//! depth, as are form fields of the same names.
+
+use std::sync::Arc;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http_body_util::BodyExt;
+use hyper::body::Body as _;
+use serde_json::Value;
+use server_core::error::ErrorResponse;
+use tracing::{debug, Level};
+
+const REDACTED: &str = "[REDACTED]";
+
+#[derive(Debug, Clone)]
+pub struct BodyLogConfig {
+    /// Bodies larger than this are not buffered.
+    pub max_bytes: usize,
+    /// JSON and form field names whose values are never logged, compared
+    /// case-insensitively.
+    pub redact: Vec<String>,
+}
+
+impl Default for BodyLogConfig {
+    fn default() -> Self {
+        BodyLogConfig {
+            max_bytes: 16 * 1024,
+            redact: [
+                "password",
+                "secret",
+                "token",
+                "access_token",
+                "refresh_token",
+                "api_key",
+                "authorization",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+}
+
+/// Why a body was passed through without being logged.
+enum Skip {
+    Streaming,
+    Binary(u64),
+    TooLarge(u64),
+}
+
+pub async fn log_bodies(
+    State(config): State<Arc<BodyLogConfig>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !tracing::enabled!(Level::DEBUG) {
+        return next.run(request).await;
+    }
+    let method = request.method().clone();
+    let uri = request.uri().clone();
+
+    let (parts, body) = request.into_parts();
+    let body = match buffer(&config, &parts.headers, body).await {
+        Ok(body) => body,
+        Err(err) => {
+            return ErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_request", err)
+                .into_response()
+        }
+    };
+    let request = Request::from_parts(
+        parts,
+        body.into_body(&config, |logged| {
+            debug!(%method, %uri, body = %logged, "request body");
+        }),
+    );
+
+    let response = next.run(request).await;
+    let status = response.status();
+    let (parts, body) = response.into_parts();
+    let body = match buffer(&config, &parts.headers, body).await {
+        Ok(body) => body,
+        Err(err) => {
+            tracing::error!(%err, "failed to buffer response body");
+            return ErrorResponse::internal().into_response();
+        }
+    };
+    Response::from_parts(
+        parts,
+        body.into_body(&config, |logged| {
+            debug!(%method, %uri, %status, body = %logged, "response body");
+        }),
+    )
+}
+
+/// A body that was either read into memory, along with its content type, or
+/// left alone.
+enum Buffered {
+    Bytes(Bytes, String),
+    Skipped(Skip, Body),
+}
+
+impl Buffered {
+    /// Log the body (or why it was skipped) and turn it back into a [`Body`].
+    fn into_body(self, config: &BodyLogConfig, log: impl FnOnce(&str)) -> Body {
+        match self {
+            Buffered::Bytes(bytes, content_type) => {
+                log(&render(config, &content_type, &bytes));
+                Body::from(bytes)
+            }
+            Buffered::Skipped(skip, body) => {
+                log(&match skip {
+                    Skip::Streaming => "<streaming, not logged>".to_string(),
+                    Skip::Binary(len) => format!("<{len} bytes of binary, not logged>"),
+                    Skip::TooLarge(len) => format!("<{len} bytes, not logged>"),
+                });
+                body
+            }
+        }
+    }
+}
+
+async fn buffer(
+    config: &BodyLogConfig,
+    headers: &HeaderMap,
+    body: Body,
+) -> Result<Buffered, String> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    if is_streaming(content_type) {
+        return Ok(Buffered::Skipped(Skip::Streaming, body));
+    }
+    let Some(len) = body.size_hint().exact() else {
+        return Ok(Buffered::Skipped(Skip::Streaming, body));
+    };
+    if len > 0 && !is_textual(content_type) {
+        return Ok(Buffered::Skipped(Skip::Binary(len), body));
+    }
+    if len > config.max_bytes as u64 {
+        return Ok(Buffered::Skipped(Skip::TooLarge(len), body));
+    }
+    match body.collect().await {
+        Ok(collected) => Ok(Buffered::Bytes(
+            collected.to_bytes(),
+            content_type.to_string(),
+        )),
+        Err(err) => Err(format!("failed to read body: {err}")),
+    }
+}
+
+fn essence(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase()
+}
+
+fn is_streaming(content_type: &str) -> bool {
+    matches!(
+        essence(content_type).as_str(),
+        "text/event-stream" | "application/x-ndjson" | "application/ndjson"
+    )
+}
+
+fn is_textual(content_type: &str) -> bool {
+    let essence = essence(content_type);
+    essence.starts_with("text/")
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+        || matches!(
+            essence.as_str(),
+            "application/json" | "application/xml" | "application/x-www-form-urlencoded"
+        )
+}
+
+/// The body as it should appear in the log: redacted form fields for form
+/// bodies, redacted JSON when it parses, lossy UTF-8 otherwise.
+fn render(config: &BodyLogConfig, content_type: &str, bytes: &[u8]) -> String {
+    if essence(content_type) == "application/x-www-form-urlencoded" {
+        return redact_form(bytes, &config.redact);
+    }
+    match serde_json::from_slice::<Value>(bytes) {
+        Ok(mut json) => {
+            redact(&mut json, &config.redact);
+            json.to_string()
+        }
+        Err(_) => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+fn redact_form(bytes: &[u8], fields: &[String]) -> String {
+    let mut form = form_urlencoded::Serializer::new(String::new());
+    for (key, value) in form_urlencoded::parse(bytes) {
+        if fields.iter().any(|f| f.eq_ignore_ascii_case(&key)) {
+            form.append_pair(&key, REDACTED);
+        } else {
+            form.append_pair(&key, &value);
+        }
+    }
+    form.finish()
+}
+
+fn redact(value: &mut Value, fields: &[String]) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if fields.iter().any(|f| f.eq_ignore_ascii_case(key)) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact(value, fields);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| redact(item, fields)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{middleware, routing::post, Router};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn fields() -> Vec<String> {
+        BodyLogConfig::default().redact
+    }
+
+    #[test]
+    fn redacts_at_any_depth() {
+        let mut value = json!({
+            "user": { "name": "ann", "Password": "hunter2" },
+            "sessions": [{ "token": "abc", "id": 1 }],
+            "api_key": { "nested": "whole value goes" },
+        });
+        redact(&mut value, &fields());
+        assert_eq!(
+            value,
+            json!({
+                "user": { "name": "ann", "Password": REDACTED },
+                "sessions": [{ "token": REDACTED, "id": 1 }],
+                "api_key": REDACTED,
+            })
+        );
+    }
+
+    #[test]
+    fn renders_json_forms_and_text() {
+        let config = BodyLogConfig::default();
+        assert_eq!(
+            render(
+                &config,
+                "application/json",
+                br#"{"title":"a","secret":"s"}"#
+            ),
+            r#"{"secret":"[REDACTED]","title":"a"}"#
+        );
+        // Keys are matched after percent-decoding.
+        assert_eq!(
+            render(
+                &config,
+                "application/x-www-form-urlencoded; charset=utf-8",
+                b"user=bob&pass%77ord=hunter2"
+            ),
+            "user=bob&password=%5BREDACTED%5D"
+        );
+        assert_eq!(
+            render(&config, "text/plain", b"plain \xff"),
+            "plain \u{fffd}"
+        );
+    }
+
+    #[test]
+    fn classifies_content_types() {
+        assert!(is_streaming("text/event-stream"));
+        assert!(is_streaming("Application/X-NDJSON; charset=utf-8"));
+        assert!(!is_streaming("application/json"));
+
+        for textual in [
+            "text/html; charset=utf-8",
+            "application/json",
+            "application/problem+json",
+            "application/atom+xml",
+            "application/x-www-form-urlencoded",
+        ] {
+            assert!(is_textual(textual), "{textual}");
+        }
+        for binary in [
+            "image/png",
+            "application/octet-stream",
+            "application/cbor",
+            "",
+        ] {
+            assert!(!is_textual(binary), "{binary:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn handler_still_gets_the_body() {
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(Level::DEBUG)
+            .with_test_writer()
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let config = Arc::new(BodyLogConfig {
+            max_bytes: 16,
+            ..BodyLogConfig::default()
+        });
+        let app = Router::new()
+            .route("/echo", post(|body: Bytes| async move { body }))
+            .layer(middleware::from_fn_with_state(config, log_bodies));
+
+        // Buffered, too large, and binary bodies all arrive unchanged.
+        for (content_type, body) in [
+            ("application/json", &br#"{"password":"x"}"#[..]),
+            ("text/plain", &b"longer than sixteen bytes"[..]),
+            ("application/octet-stream", &b"\x00\x01\x02"[..]),
+        ] {
+            let request = Request::post("/echo")
+                .header(header::CONTENT_TYPE, content_type)
+                .body(Body::from(body))
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let echoed = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(&echoed[..], body, "{content_type}");
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use axum::{
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use server_core::error::ErrorResponse;
+
+/// A single invalid field in a request body.
+#[derive(Debug, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &'static str, message: impl Into<String>) -> Self {
+        FieldError {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+/// Errors returned by the handlers, rendered as the shared JSON error body.
+#[derive(Debug)]
+pub enum AppError {
+    /// The body or query string could not be parsed at all.
+    BadRequest {
+        status: StatusCode,
+        message: String,
+    },
+    /// The body parsed but some fields hold invalid values.
+    Validation(Vec<FieldError>),
+    NotFound(String),
+    /// The repository failed; details are logged, not returned.
+    Internal(String),
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        match self {
+            AppError::BadRequest { status, message } => {
+                ErrorResponse::new(status, "invalid_request", message)
+            }
+            AppError::Validation(fields) => ErrorResponse::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "validation_failed",
+                "request contains invalid fields",
+            )
+            .with_detail("fields", fields),
+            AppError::NotFound(message) => {
+                ErrorResponse::new(StatusCode::NOT_FOUND, "not_found", message)
+            }
+            AppError::Internal(message) => {
+                tracing::error!(%message, "repository error");
+                ErrorResponse::internal()
+            }
+        }
+        .into_response()
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        AppError::BadRequest {
+            status: rejection.status(),
+            message: rejection.body_text(),
+        }
+    }
+}
+
+impl From<QueryRejection> for AppError {
+    fn from(rejection: QueryRejection) -> Self {
+        AppError::BadRequest {
+            status: rejection.status(),
+            message: rejection.body_text(),
+        }
+    }
+}
+
+impl From<PathRejection> for AppError {
+    fn from(rejection: PathRejection) -> Self {
+        AppError::BadRequest {
+            status: rejection.status(),
+            message: rejection.body_text(),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Todo service: the router and its middleware, served by `main.rs`.
+
+pub mod access;
+pub mod body_log;
+pub mod error;
+pub mod repository;
+pub mod todos;
+
+use std::sync::Arc;
+
+use axum::{error_handling::HandleErrorLayer, middleware, response::Html, routing::get, Router};
+use server_core::{health::Readiness, metrics::Metrics};
+use tower::{filter::FilterLayer, ServiceBuilder};
+
+use access::AccessPolicy;
+use body_log::BodyLogConfig;
+use repository::TodoRepository;
+
+/// The service's routes behind body logging and the access gate.
+/// `/metrics` is mounted here so it sits behind the gate too.
+pub fn app<R: TodoRepository>(repo: R, policy: AccessPolicy, metrics: &Metrics) -> Router {
+    Router::new()
+        .route("/", get(handler))
+        .merge(todos::routes(repo))
+        .merge(metrics.routes())
+        .layer(middleware::from_fn_with_state(
+            Arc::new(BodyLogConfig::default()),
+            body_log::log_bodies,
+        ))
+        .layer(
+            ServiceBuilder::new()
+                .layer(HandleErrorLayer::new(access::handle_rejection))
+                .layer(FilterLayer::new(policy)),
+        )
+}
+
+/// Readiness checks for `/readyz`: the repository, and the key file if one
+/// is configured.
+pub fn readiness<R: TodoRepository>(repo: &R, policy: &AccessPolicy) -> Readiness {
+    let mut readiness = Readiness::new().with_check("repository", {
+        let repo = repo.clone();
+        move || {
+            let repo = repo.clone();
+            async move { repo.ping().await.map_err(|err| err.0) }
+        }
+    });
+    if let Some(keys) = policy.keys() {
+        let path = keys.path().to_path_buf();
+        readiness = readiness.with_check("api_keys", move || {
+            let path = path.clone();
+            async move {
+                tokio::fs::metadata(&path)
+                    .await
+                    .map(|_| ())
+                    .map_err(|err| format!("{}: {err}", path.display()))
+            }
+        });
+    }
+    readiness
+}
+
+async fn handler() -> Html<&'static str> {
+    Html("<h1>Hello Axum</h1>")
+}
@@ -1,20 +1,44 @@
-use axum::{
-    body::{Body,Bytes},
-    extract::Request,
-    http::StatusCode,
-    routing::get,routing::post,Json,Router,response::Html
-};
-use serde::{Deserialize,Serialize};
+use std::{process::ExitCode, time::Duration};
 
-#[tokio::main]
-async fn main() {
-    //init tracing
-    tracing_subscriber::fmt::init();
-    let app = Router::new().route("/", get(handler));
-    let listener
-        = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
-     axum::serve(listener, app).await.unwrap();
+use axum_learn::{access::AccessPolicy, repository::InMemoryRepository};
+use clap::Parser;
+use server_core::{Server, ServerArgs};
+
+#[derive(Parser)]
+struct Cli {
+    #[command(flatten)]
+    server: ServerArgs,
 }
-async fn handler() -> Html<&'static str>{
-    Html("<h1>Hello Axum</h1>")
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let server = match Server::from_args(&cli.server) {
+        Ok(server) => server,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::from(2);
+        }
+    };
+    let policy = match AccessPolicy::from_env() {
+        Ok(policy) => policy,
+        Err(err) => {
+            tracing::error!(%err, "invalid access configuration");
+            return ExitCode::from(2);
+        }
+    };
+    if let Some(keys) = policy.keys() {
+        keys.spawn_reloader(Duration::from_secs(5), server.shutdown());
+    }
+    if !policy.is_enabled() {
+        tracing::warn!("no ACCESS_KEYS_FILE or ACCESS_ALLOW set; every client is allowed");
+    }
+    let repo = InMemoryRepository::default();
+    let readiness = axum_learn::readiness(&repo, &policy);
+    let app = axum_learn::app(repo, policy, server.metrics());
+    server
+        .readiness(readiness)
+        .without_metrics_route()
+        .serve(app)
+        .await
 }
\ No newline at end of file
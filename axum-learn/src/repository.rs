@@ -0,0 +1,99 @@
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    sync::{Arc, RwLock},
+};
+
+use crate::todos::{NewTodo, Todo, TodoChanges, TodoFilter};
+
+/// Error raised by a storage backend.
+#[derive(Debug)]
+pub struct RepositoryError(pub String);
+
+/// Storage for todos. Handlers only talk to this trait, so a database-backed
+/// implementation can replace [`InMemoryRepository`] without touching them.
+pub trait TodoRepository: Clone + Send + Sync + 'static {
+    fn list(
+        &self,
+        filter: TodoFilter,
+    ) -> impl Future<Output = Result<Vec<Todo>, RepositoryError>> + Send;
+
+    fn get(&self, id: u64) -> impl Future<Output = Result<Option<Todo>, RepositoryError>> + Send;
+
+    fn create(&self, todo: NewTodo) -> impl Future<Output = Result<Todo, RepositoryError>> + Send;
+
+    /// Apply `changes` to an existing todo; `None` if there is no such todo.
+    fn update(
+        &self,
+        id: u64,
+        changes: TodoChanges,
+    ) -> impl Future<Output = Result<Option<Todo>, RepositoryError>> + Send;
+
+    /// Returns whether a todo was deleted.
+    fn delete(&self, id: u64) -> impl Future<Output = Result<bool, RepositoryError>> + Send;
+
+    /// Whether the backend can currently serve requests; used by `/readyz`.
+    fn ping(&self) -> impl Future<Output = Result<(), RepositoryError>> + Send {
+        async { Ok(()) }
+    }
+}
+
+#[derive(Default)]
+struct Store {
+    next_id: u64,
+    todos: BTreeMap<u64, Todo>,
+}
+
+/// Keeps todos in a map behind a lock; ids start at 1 and are never reused.
+#[derive(Clone, Default)]
+pub struct InMemoryRepository {
+    store: Arc<RwLock<Store>>,
+}
+
+impl TodoRepository for InMemoryRepository {
+    async fn list(&self, filter: TodoFilter) -> Result<Vec<Todo>, RepositoryError> {
+        let store = self.store.read().unwrap();
+        Ok(store
+            .todos
+            .values()
+            .filter(|todo| filter.completed.is_none_or(|c| todo.completed == c))
+            .skip(filter.offset)
+            .take(filter.limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn get(&self, id: u64) -> Result<Option<Todo>, RepositoryError> {
+        Ok(self.store.read().unwrap().todos.get(&id).cloned())
+    }
+
+    async fn create(&self, todo: NewTodo) -> Result<Todo, RepositoryError> {
+        let mut store = self.store.write().unwrap();
+        store.next_id += 1;
+        let todo = Todo {
+            id: store.next_id,
+            title: todo.title,
+            completed: todo.completed,
+        };
+        store.todos.insert(todo.id, todo.clone());
+        Ok(todo)
+    }
+
+    async fn update(&self, id: u64, changes: TodoChanges) -> Result<Option<Todo>, RepositoryError> {
+        let mut store = self.store.write().unwrap();
+        let Some(todo) = store.todos.get_mut(&id) else {
+            return Ok(None);
+        };
+        if let Some(title) = changes.title {
+            todo.title = title;
+        }
+        if let Some(completed) = changes.completed {
+            todo.completed = completed;
+        }
+        Ok(Some(todo.clone()))
+    }
+
+    async fn delete(&self, id: u64) -> Result<bool, RepositoryError> {
+        Ok(self.store.write().unwrap().todos.remove(&id).is_some())
+    }
+}
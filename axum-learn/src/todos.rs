@@ -0,0 +1,227 @@
+//! `/todos` resource: create, list, read, update and delete todo items.
+
+use axum::{
+    extract::{
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        Path, Query, State,
+    },
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, FieldError};
+use crate::repository::{RepositoryError, TodoRepository};
+
+pub const MAX_TITLE_LEN: usize = 200;
+pub const DEFAULT_LIMIT: usize = 100;
+pub const MAX_LIMIT: usize = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Todo {
+    pub id: u64,
+    pub title: String,
+    pub completed: bool,
+}
+
+/// A validated todo ready to be stored.
+#[derive(Debug)]
+pub struct NewTodo {
+    pub title: String,
+    pub completed: bool,
+}
+
+/// Validated partial update; `None` fields are left unchanged.
+#[derive(Debug, Default)]
+pub struct TodoChanges {
+    pub title: Option<String>,
+    pub completed: Option<bool>,
+}
+
+#[derive(Debug)]
+pub struct TodoFilter {
+    pub completed: Option<bool>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreateTodo {
+    title: Option<String>,
+    #[serde(default)]
+    completed: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateTodo {
+    title: Option<String>,
+    completed: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct ListTodos {
+    completed: Option<bool>,
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct TodoList {
+    todos: Vec<Todo>,
+    offset: usize,
+    limit: usize,
+}
+
+/// Trim `title` and check it is neither empty nor too long.
+fn validate_title(title: &str, errors: &mut Vec<FieldError>) -> String {
+    let title = title.trim();
+    if title.is_empty() {
+        errors.push(FieldError::new("title", "must not be empty"));
+    } else if title.chars().count() > MAX_TITLE_LEN {
+        errors.push(FieldError::new(
+            "title",
+            format!("must be at most {MAX_TITLE_LEN} characters"),
+        ));
+    }
+    title.to_string()
+}
+
+impl CreateTodo {
+    fn validate(self) -> Result<NewTodo, AppError> {
+        let mut errors = Vec::new();
+        let title = match self.title {
+            Some(title) => validate_title(&title, &mut errors),
+            None => {
+                errors.push(FieldError::new("title", "is required"));
+                String::new()
+            }
+        };
+        if !errors.is_empty() {
+            return Err(AppError::Validation(errors));
+        }
+        Ok(NewTodo {
+            title,
+            completed: self.completed,
+        })
+    }
+}
+
+impl UpdateTodo {
+    fn validate(self) -> Result<TodoChanges, AppError> {
+        let mut errors = Vec::new();
+        let title = self.title.map(|title| validate_title(&title, &mut errors));
+        if !errors.is_empty() {
+            return Err(AppError::Validation(errors));
+        }
+        Ok(TodoChanges {
+            title,
+            completed: self.completed,
+        })
+    }
+}
+
+impl ListTodos {
+    fn validate(self) -> Result<TodoFilter, AppError> {
+        let limit = self.limit.unwrap_or(DEFAULT_LIMIT);
+        if !(1..=MAX_LIMIT).contains(&limit) {
+            return Err(AppError::Validation(vec![FieldError::new(
+                "limit",
+                format!("must be between 1 and {MAX_LIMIT}"),
+            )]));
+        }
+        Ok(TodoFilter {
+            completed: self.completed,
+            offset: self.offset,
+            limit,
+        })
+    }
+}
+
+impl From<RepositoryError> for AppError {
+    fn from(err: RepositoryError) -> Self {
+        AppError::Internal(err.0)
+    }
+}
+
+fn not_found(id: u64) -> AppError {
+    AppError::NotFound(format!("no todo with id {id}"))
+}
+
+/// Routes for the todo resource, backed by `repo`.
+pub fn routes<R: TodoRepository>(repo: R) -> Router {
+    Router::new()
+        .route("/todos", get(list::<R>).post(create::<R>))
+        .route(
+            "/todos/:id",
+            get(read::<R>).patch(update::<R>).delete(delete::<R>),
+        )
+        .with_state(repo)
+}
+
+async fn list<R: TodoRepository>(
+    State(repo): State<R>,
+    query: Result<Query<ListTodos>, QueryRejection>,
+) -> Result<Json<TodoList>, AppError> {
+    let Query(query) = query?;
+    let filter = query.validate()?;
+    let (offset, limit) = (filter.offset, filter.limit);
+    let todos = repo.list(filter).await?;
+    Ok(Json(TodoList {
+        todos,
+        offset,
+        limit,
+    }))
+}
+
+async fn create<R: TodoRepository>(
+    State(repo): State<R>,
+    body: Result<Json<CreateTodo>, JsonRejection>,
+) -> Result<impl IntoResponse, AppError> {
+    let Json(body) = body?;
+    let todo = repo.create(body.validate()?).await?;
+    let location = format!("/todos/{}", todo.id);
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, location)],
+        Json(todo),
+    ))
+}
+
+async fn read<R: TodoRepository>(
+    State(repo): State<R>,
+    id: Result<Path<u64>, PathRejection>,
+) -> Result<Json<Todo>, AppError> {
+    let Path(id) = id?;
+    repo.get(id).await?.map(Json).ok_or_else(|| not_found(id))
+}
+
+async fn update<R: TodoRepository>(
+    State(repo): State<R>,
+    id: Result<Path<u64>, PathRejection>,
+    body: Result<Json<UpdateTodo>, JsonRejection>,
+) -> Result<Json<Todo>, AppError> {
+    let Path(id) = id?;
+    let Json(body) = body?;
+    let changes = body.validate()?;
+    repo.update(id, changes)
+        .await?
+        .map(Json)
+        .ok_or_else(|| not_found(id))
+}
+
+async fn delete<R: TodoRepository>(
+    State(repo): State<R>,
+    id: Result<Path<u64>, PathRejection>,
+) -> Result<StatusCode, AppError> {
+    let Path(id) = id?;
+    if repo.delete(id).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(not_found(id))
+    }
+}
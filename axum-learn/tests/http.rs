@@ -0,0 +1,174 @@
+use std::{fs, path::PathBuf};
+
+use axum::http::StatusCode;
+use axum_learn::{
+    access::{AccessPolicy, KeyStore},
+    repository::InMemoryRepository,
+};
+use serde_json::{json, Value};
+use server_core::{metrics::Metrics, testing::TestClient};
+
+fn client(policy: AccessPolicy) -> TestClient {
+    let app = axum_learn::app(InMemoryRepository::default(), policy, &Metrics::new());
+    TestClient::new(app)
+}
+
+/// A key file unique to `test`, so tests can run in parallel.
+fn key_file(test: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("axum-learn-{test}-{}.keys", std::process::id()));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[tokio::test]
+async fn index_page() {
+    client(AccessPolicy::default())
+        .get("/")
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_html_snapshot("index");
+}
+
+#[tokio::test]
+async fn todo_lifecycle() {
+    let client = client(AccessPolicy::default());
+
+    let created = client
+        .post("/todos")
+        .json(&json!({ "title": "  write tests  " }))
+        .send()
+        .await;
+    created
+        .assert_status(StatusCode::CREATED)
+        .assert_header("location", "/todos/1");
+    let todo: Value = created.json();
+    assert_eq!(
+        todo,
+        json!({ "id": 1, "title": "write tests", "completed": false })
+    );
+
+    let updated: Value = client
+        .patch("/todos/1")
+        .json(&json!({ "completed": true }))
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .json();
+    assert_eq!(updated["completed"], true);
+
+    let list: Value = client
+        .get("/todos?completed=true")
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .json();
+    assert_eq!(list["todos"].as_array().unwrap().len(), 1);
+
+    client
+        .delete("/todos/1")
+        .send()
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    client
+        .get("/todos/1")
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+}
+
+#[tokio::test]
+async fn validation_errors() {
+    let client = client(AccessPolicy::default());
+
+    let response = client
+        .post("/todos")
+        .json(&json!({ "title": "   " }))
+        .send()
+        .await;
+    response.assert_error(StatusCode::UNPROCESSABLE_ENTITY, "validation_failed");
+    assert_eq!(response.json::<Value>()["fields"][0]["field"], "title");
+
+    client
+        .get("/todos?limit=0")
+        .send()
+        .await
+        .assert_error(StatusCode::UNPROCESSABLE_ENTITY, "validation_failed");
+    client
+        .post("/todos")
+        .json(&json!({ "title": "x", "colour": "red" }))
+        .send()
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    client
+        .get("/todos/abc")
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn api_keys_are_required() {
+    let path = key_file("keys", "# team\nsecret-one\n");
+    let keys = KeyStore::load(&path).unwrap();
+    let policy = AccessPolicy::new(Some(keys.clone()), Vec::new(), vec!["/healthz".into()]);
+    let client = client(policy);
+
+    let missing = client.get("/todos").send().await;
+    missing
+        .assert_error(StatusCode::UNAUTHORIZED, "unauthorized")
+        .assert_header("www-authenticate", "Bearer");
+    client
+        .get("/todos")
+        .header("x-api-key", "wrong")
+        .send()
+        .await
+        .assert_error(StatusCode::UNAUTHORIZED, "unauthorized");
+    client
+        .get("/todos")
+        .header("authorization", "Bearer secret-one")
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+
+    // Exempt paths skip the gate; here the route itself does not exist.
+    client
+        .get("/healthz")
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    fs::write(&path, "secret-two\n").unwrap();
+    keys.reload().unwrap();
+    client
+        .get("/todos")
+        .header("x-api-key", "secret-one")
+        .send()
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    client
+        .get("/todos")
+        .header("x-api-key", "secret-two")
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn allowlist() {
+    let policy = AccessPolicy::new(None, vec!["10.0.0.0/8".parse().unwrap()], Vec::new());
+    let client = client(policy);
+
+    client
+        .get("/todos")
+        .peer("10.1.2.3:5000".parse().unwrap())
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    client
+        .get("/todos")
+        .send()
+        .await
+        .assert_error(StatusCode::FORBIDDEN, "forbidden");
+}
@@ -151,19 +151,19 @@ pub fn crossbeam_select_macro() {
     use crossbeam_channel::{unbounded, select, Sender, Receiver};
     use std::thread;
 //create two channels
-    let (sender1, receiver1): (Sender<&str>, Receiver<&str>) = unbounded();
-    let (sender2, receiver2): (Sender<&str>, Receiver<&str>) = unbounded();
+    let (sender1, receiver1): (Sender<String>, Receiver<String>) = unbounded();
+    let (sender2, receiver2): (Sender<String>, Receiver<String>) = unbounded();
     //create a producer ,send message to channel one
     let producer1 = thread::spawn(move || {
         for i in 0..5 {
-            sender1.send(&format!("Channel 1:Message {}", i)).unwrap();
+            sender1.send(format!("Channel 1:Message {}", i)).unwrap();
             thread::sleep(std::time::Duration::from_millis(200));
         }
     });
     //create second producer send message to channel two
     let producer2 = thread::spawn(move || {
         for i in 0..5 {
-            sender2.send(&format!("Channel 2:Message:{}", i)).unwrap();
+            sender2.send(format!("Channel 2:Message:{}", i)).unwrap();
             thread::sleep(std::time::Duration::from_millis(300));
         }
     });
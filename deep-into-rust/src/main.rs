@@ -162,6 +162,8 @@ fn main() {
     // crossbeam_channel_bounded();
     // crossbeam_channel_unbounded();
     crossbeam_select_macro();
+    // process::socket_handoff::socket_handoff_example();
+    // process::socket_handoff::scm_rights_example();
 }
 
 fn fibonacci(n: u128) -> u128 {
@@ -1 +1,3 @@
-pub mod process_learn;
\ No newline at end of file
+pub mod process_learn;
+#[cfg(target_os = "linux")]
+pub mod socket_handoff;
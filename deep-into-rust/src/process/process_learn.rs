@@ -26,35 +26,8 @@ pub fn create_process_example() {
 }
 
 
-//传递给子进程打开的文件,实现子进程恢复套接字的代码涉及到子进程解析命令行参数并使用nix库的dup2函数将文件描述符复制到正确的位置.
-
-// pub fn fork_child_process_example() {
-//     use std::net::{TcpListener, TcpStream};
-//     use std::os::windows::io::AsRawSocket;
-//     use nix::unistd::{dup2, close, ForkResult};
-//     let listener = TcpListener::bind("127.0.0.1:8000").expect("Failed to bind to address");
-//     let (stream, _) = listener.accept().expect("Failed to accept connection");
-//     let socket_fd = stream.as_raw_socket();
-//     let backup_fd = dup2(socket_fd, 10).expect("Failed to duplicate file");
-//     //将套接字传递给子进行
-//     match unsafe { nix::unistd::fork() } {
-//         Ok(ForkResult::Parent { child }) => {
-//             close(backup_fd).expect("Failed to close backup file desc");
-//             println!("Parent process.Child PID:{child}");
-//             println!("Parent process.Child PID:{:?}", child);
-//         }
-//         Ok(ForkResult::Child) => {
-//             dup2(backup_fd, socket_fd).expect("Failed to restore file desc");
-//             close(backup_fd).expect("Failed to close backup fd");
-//             let mut buffer = [0; 1024];
-//             let _ = TcpStream::from_raw_fd(socket_fd).read(&mut buffer);
-//             println!("Child process.Received:{:?}", String::from_utf8_lossy(&buffer));
-//         }
-//         Err(_) => {
-//             eprintln!("Fork failed");
-//         }
-//     }
-// }
+//传递给子进程打开的文件,子进程把继承的文件描述符恢复成套接字.Linux上的实现见socket_handoff模块:
+//用dup2把监听套接字放到3号描述符再exec新程序,或者通过Unix域套接字用SCM_RIGHTS发送.
 
 ///std::process::Child类型可以控制子进程,std::process::Command.spawn()返回Child,并提供一些方法来与子进程进行交互,等待其结束以及发送信号等.
 
@@ -0,0 +1,225 @@
+//! 零停机重启:把正在监听的套接字交给新exec的子进程.
+//!
+//! 监听套接字在内核里只有一个,多个进程持有它的文件描述符时,谁调用accept谁就拿到连接.
+//! 所以升级二进制时,旧进程把描述符交给新进程,新进程开始accept后,旧进程停止accept,
+//! 处理完手上的连接再退出.排队中的连接留在内核的backlog里,由新进程接着处理,一个也不会丢.
+//!
+//! 交接描述符有两种方式:
+//! - 继承:fork之后exec之前,在子进程里用dup2把描述符放到3号,并清掉FD_CLOEXEC,
+//!   再设置环境变量`LISTEN_FDS=1`.这和systemd的socket activation约定一致,
+//!   server-core的`Listener::bind`可以直接接管.见[`spawn_with_listener`]和[`inherited_listener`].
+//! - SCM_RIGHTS:通过Unix域套接字把描述符作为辅助数据发给另一个进程,适合新进程不是旧进程的子进程的情况.
+//!   见[`send_listener`]和[`recv_listener`].
+
+use std::ffi::OsStr;
+use std::io::{self, IoSlice, IoSliceMut, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use nix::fcntl::{fcntl, FcntlArg, FdFlag};
+use nix::sys::socket::{
+    getsockname, recvmsg, sendmsg, AddressFamily, ControlMessage, ControlMessageOwned, MsgFlags,
+    SockaddrLike, SockaddrStorage,
+};
+use nix::unistd::dup2;
+
+/// socket activation约定的第一个描述符.
+pub const LISTEN_FDS_START: RawFd = 3;
+
+/// 启动`program`,并把`listener`作为3号描述符交给它,同时设置`LISTEN_FDS=1`.
+/// 不设置`LISTEN_PID`:spawn之前还不知道子进程的pid,而在pre_exec里调用setenv不是async-signal-safe的.
+/// 接收方在`LISTEN_PID`缺省时应该接受这个描述符,server-core就是这样做的.
+///
+/// 子进程里3号描述符原来的内容会被覆盖.std和nix打开的描述符都带FD_CLOEXEC,exec时本来就会关闭,
+/// 所以只有父进程特意设为可继承的3号描述符才会丢失,这种情况下不要用这个函数.
+pub fn spawn_with_listener<I, S>(listener: &impl AsRawFd, program: impl AsRef<OsStr>, args: I) -> io::Result<Child>
+    where I: IntoIterator<Item=S>, S: AsRef<OsStr> {
+    // 先复制到大于3的描述符:监听套接字可能就在3号,甚至在0到2号(进程启动时关闭了标准输入输出),
+    // 而Command会在pre_exec之前把标准输入输出dup2到0到2号上.复制品带FD_CLOEXEC,spawn之后关闭.
+    let handoff = fcntl(listener.as_raw_fd(), FcntlArg::F_DUPFD_CLOEXEC(LISTEN_FDS_START + 1))?;
+    // SAFETY: fcntl刚创建了这个描述符,只有这里拥有它.
+    let handoff = unsafe { OwnedFd::from_raw_fd(handoff) };
+    let fd = handoff.as_raw_fd();
+    let mut command = Command::new(program);
+    command.args(args).env("LISTEN_FDS", "1").env_remove("LISTEN_PID");
+    // pre_exec的闭包在fork之后,exec之前,在子进程里运行,只能调用async-signal-safe的函数.
+    // dup2是直接的系统调用,没有分配内存也不加锁.fd大于3,所以dup2一定会生效,
+    // dup2出来的新描述符不带FD_CLOEXEC,exec之后仍然打开.
+    unsafe {
+        command.pre_exec(move || {
+            dup2(fd, LISTEN_FDS_START)?;
+            Ok(())
+        });
+    }
+    command.spawn()
+}
+
+/// 子进程一侧:如果父进程通过`LISTEN_FDS`传来了TCP监听套接字,就接管它.
+/// 接管之前先检查3号描述符确实是TCP套接字,否则不碰它,因为它可能属于进程里的别的东西.
+pub fn inherited_listener() -> io::Result<Option<TcpListener>> {
+    let fds = match std::env::var("LISTEN_FDS") {
+        Ok(fds) => fds,
+        Err(_) => return Ok(None),
+    };
+    if let Ok(pid) = std::env::var("LISTEN_PID") {
+        if pid.parse() != Ok(std::process::id()) {
+            return Ok(None);
+        }
+    }
+    if fds.parse::<u32>().map_or(true, |n| n == 0) {
+        return Ok(None);
+    }
+    let addr: SockaddrStorage = getsockname(LISTEN_FDS_START)?;
+    if !matches!(addr.family(), Some(AddressFamily::Inet | AddressFamily::Inet6)) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "fd 3 is not a TCP socket"));
+    }
+    //不再传给以后启动的子进程
+    std::env::remove_var("LISTEN_FDS");
+    std::env::remove_var("LISTEN_PID");
+    fcntl(LISTEN_FDS_START, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC))?;
+    // SAFETY: 上面确认了3号描述符是父进程交过来的TCP套接字,进程里没有别人拥有它.
+    Ok(Some(unsafe { TcpListener::from_raw_fd(LISTEN_FDS_START) }))
+}
+
+/// 等待`settle`这么久,确认子进程没有启动就退出.子进程提前退出时返回错误,
+/// 这时旧进程应该继续服务,而不是把连接扔给一个已经不存在的进程.
+pub fn wait_until_started(child: &mut Child, settle: Duration) -> io::Result<()> {
+    let deadline = Instant::now() + settle;
+    while Instant::now() < deadline {
+        if let Some(status) = child.try_wait()? {
+            return Err(io::Error::new(io::ErrorKind::Other, format!("child exited early: {status}")));
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    Ok(())
+}
+
+/// 通过Unix域套接字`channel`,用SCM_RIGHTS把`listener`的描述符发给对端.
+/// 内核会在接收方进程里创建一个新的描述符,指向同一个监听套接字.
+pub fn send_listener(channel: &UnixStream, listener: &impl AsRawFd) -> io::Result<()> {
+    let fds = [listener.as_raw_fd()];
+    //辅助数据必须随至少一个字节的普通数据一起发送
+    let iov = [IoSlice::new(b"L")];
+    let cmsg = [ControlMessage::ScmRights(&fds)];
+    sendmsg::<()>(channel.as_raw_fd(), &iov, &cmsg, MsgFlags::empty(), None)?;
+    Ok(())
+}
+
+/// 从`channel`接收[`send_listener`]发来的监听套接字.
+pub fn recv_listener(channel: &UnixStream) -> io::Result<TcpListener> {
+    let mut byte = [0u8; 1];
+    let mut iov = [IoSliceMut::new(&mut byte)];
+    let mut cmsg_buffer = nix::cmsg_space!([RawFd; 1]);
+    // MSG_CMSG_CLOEXEC:收到的描述符直接带上FD_CLOEXEC,不会泄漏给之后exec的程序
+    let msg = recvmsg::<()>(channel.as_raw_fd(), &mut iov, Some(&mut cmsg_buffer), MsgFlags::MSG_CMSG_CLOEXEC)?;
+    for cmsg in msg.cmsgs() {
+        if let ControlMessageOwned::ScmRights(fds) = cmsg {
+            if let Some(&fd) = fds.first() {
+                // SAFETY: 这是内核刚为本进程创建的描述符,只有这里拥有它.
+                return Ok(unsafe { TcpListener::from_raw_fd(fd) });
+            }
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidData, "no file descriptor in message"))
+}
+
+/// 一个极简的HTTP服务,用非阻塞accept轮询`stop`,这样可以在不关闭共享监听套接字的情况下停止accept.
+/// 注意不能对监听套接字调用shutdown,它会连同子进程那一份一起关掉.
+fn serve_until(listener: &TcpListener, stop: &AtomicBool, in_flight: &Arc<AtomicUsize>) -> io::Result<()> {
+    listener.set_nonblocking(true)?;
+    while !stop.load(Ordering::SeqCst) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                in_flight.fetch_add(1, Ordering::SeqCst);
+                let in_flight = in_flight.clone();
+                thread::spawn(move || {
+                    let _ = handle_connection(stream);
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(10)),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    let mut buffer = [0; 1024];
+    let _ = stream.read(&mut buffer)?;
+    //模拟一个慢请求,旧进程退出之前要等它处理完
+    thread::sleep(Duration::from_millis(500));
+    let body = format!("served by pid {}\n", std::process::id());
+    write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body)
+}
+
+/// 演示通过继承描述符实现零停机重启.在main里调用它:
+/// 第一次运行时绑定127.0.0.1:8000,服务5秒后exec自己(std::env::current_exe)并把监听套接字交过去,
+/// 然后停止accept,等手上的请求处理完再退出.新进程走到这里时发现`LISTEN_FDS`,直接接管套接字继续服务.
+/// 在另一个终端里执行`while true; do curl -s 127.0.0.1:8000; done`,可以看到pid变化但没有失败的请求.
+pub fn socket_handoff_example() {
+    let stop = AtomicBool::new(false);
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    if let Some(listener) = inherited_listener().expect("Failed to adopt inherited socket") {
+        println!("pid {} adopted {:?}", std::process::id(), listener.local_addr());
+        serve_until(&listener, &stop, &in_flight).expect("Failed to serve");
+        return;
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:8000").expect("Failed to bind to address");
+    println!("pid {} listening on {:?}", std::process::id(), listener.local_addr());
+    thread::scope(|s| {
+        let server = s.spawn(|| serve_until(&listener, &stop, &in_flight));
+        thread::sleep(Duration::from_secs(5));
+
+        let exe = std::env::current_exe().expect("Failed to find current executable");
+        let mut child = spawn_with_listener(&listener, exe, std::env::args_os().skip(1)).expect("Failed to start new process");
+        if let Err(e) = wait_until_started(&mut child, Duration::from_millis(500)) {
+            //新进程没起来,旧进程继续服务
+            eprintln!("handoff failed: {e}");
+            server.join().unwrap().expect("Failed to serve");
+            return;
+        }
+        println!("pid {} handed the socket to pid {}, draining", std::process::id(), child.id());
+        stop.store(true, Ordering::SeqCst);
+        server.join().unwrap().expect("Failed to serve");
+    });
+    //停止accept之后关闭自己这一份描述符,套接字仍由子进程持有
+    drop(listener);
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while in_flight.load(Ordering::SeqCst) > 0 && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(10));
+    }
+    println!("pid {} drained, exiting", std::process::id());
+}
+
+/// 演示SCM_RIGHTS:一个线程通过Unix域套接字收到监听套接字后接受连接.
+/// 发送方发送后关闭自己的描述符,接收方的描述符不受影响.在两个进程之间用法完全一样.
+pub fn scm_rights_example() {
+    let (sender, receiver) = UnixStream::pair().expect("Failed to create socket pair");
+    let acceptor = thread::spawn(move || {
+        let listener = recv_listener(&receiver).expect("Failed to receive listener");
+        let (stream, _) = listener.accept().expect("Failed to accept connection");
+        handle_connection(stream).expect("Failed to handle connection");
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind to address");
+    let addr = listener.local_addr().unwrap();
+    send_listener(&sender, &listener).expect("Failed to send listener");
+    drop(listener);
+
+    let mut stream = TcpStream::connect(addr).expect("Failed to connect");
+    stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    println!("Response:{response}");
+    acceptor.join().unwrap();
+}
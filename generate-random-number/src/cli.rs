@@ -0,0 +1,634 @@
+use std::{
+    fs,
+    io::{self, BufRead, BufWriter, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use server_core::ServerArgs;
+
+use crate::dice::{DiceResponse, Expr};
+use crate::distribution::{DistKind, DistParameters};
+use crate::fairness::{self, LedgerView};
+use crate::generator::{csv_record, ndjson_record, ParamError, Sampler, CSV_HEADER};
+use crate::lists;
+use crate::ratelimit::{RateLimitConfig, RouteLimit};
+use crate::seed::{SeedParameters, SequenceInfo, Source};
+use crate::selftest::{self, GeneratorKind, SelftestParameters};
+use crate::token::{TokenKind, TokenParameters, TokenSpec};
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Random number HTTP service and command-line generator"
+)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    #[command(flatten)]
+    pub serve: ServeArgs,
+}
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Directory holding `sina.html` and its `sina_files/` assets. Without it
+    /// the copy of `sina.html` built into the binary is served and
+    /// `/sina_files/` requests are 404s
+    #[arg(long, env = "STATIC_ROOT")]
+    pub static_root: Option<PathBuf>,
+    /// Per-client limit for a route prefix as PREFIX=BURST:PER_SECOND, e.g.
+    /// `/random=50:10`; `/` sets the default (100:20). May be repeated
+    #[arg(long = "rate-limit", env = "RATE_LIMITS", value_delimiter = ',')]
+    pub rate_limits: Vec<RouteLimit>,
+    /// Requests each client may make per UTC day
+    #[arg(long, env = "DAILY_QUOTA")]
+    pub daily_quota: Option<u64>,
+    /// File that daily quota usage is saved to and restored from
+    #[arg(long, env = "QUOTA_SNAPSHOT")]
+    pub quota_snapshot: Option<PathBuf>,
+    /// API key that gets its own limits instead of sharing its IP address's;
+    /// may be repeated
+    #[arg(
+        long = "api-key",
+        env = "API_KEYS",
+        value_delimiter = ',',
+        hide_env_values = true
+    )]
+    pub api_keys: Vec<String>,
+    #[command(flatten)]
+    pub server: ServerArgs,
+}
+
+impl ServeArgs {
+    pub fn rate_limit_config(&self) -> RateLimitConfig {
+        let mut config = RateLimitConfig {
+            daily_quota: self.daily_quota,
+            snapshot: self.quota_snapshot.clone(),
+            api_keys: self.api_keys.clone(),
+            ..RateLimitConfig::default()
+        };
+        for rule in &self.rate_limits {
+            config.set_route(rule.clone());
+        }
+        config
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run the HTTP server (the default when no command is given)
+    Serve(ServeArgs),
+    #[command(flatten)]
+    Offline(OfflineCommand),
+}
+
+/// Commands that run without starting the HTTP server. They use the same
+/// generators as the HTTP endpoints, so the same seed gives the same values.
+#[derive(Subcommand)]
+pub enum OfflineCommand {
+    /// Uniform integers in [start, end)
+    Int {
+        #[command(flatten)]
+        args: IntArgs,
+        #[command(flatten)]
+        output: GenerateArgs,
+    },
+    /// Uniform floats in [start, end)
+    Float {
+        #[command(flatten)]
+        args: FloatArgs,
+        #[command(flatten)]
+        output: GenerateArgs,
+    },
+    /// Booleans that are true with probability p
+    Bool {
+        #[command(flatten)]
+        args: BoolArgs,
+        #[command(flatten)]
+        output: GenerateArgs,
+    },
+    /// Non-uniform distributions
+    Dist {
+        #[command(flatten)]
+        args: DistArgs,
+        #[command(flatten)]
+        output: GenerateArgs,
+    },
+    /// Roll dice notation such as `4d6kh3+2`
+    Dice(DiceArgs),
+    /// Passwords, tokens, encoded random bytes and UUIDs from the OS CSPRNG
+    Token(TokenArgs),
+    /// Shuffle items given as arguments or as lines on stdin
+    Shuffle(ListArgs),
+    /// Pick k distinct items
+    Sample {
+        #[arg(long)]
+        k: usize,
+        #[command(flatten)]
+        list: ListArgs,
+    },
+    /// Pick items with replacement, optionally weighted
+    Pick {
+        /// Comma-separated weights, one per item
+        #[arg(long)]
+        weights: Option<String>,
+        #[arg(long, default_value_t = 1)]
+        count: usize,
+        #[command(flatten)]
+        list: ListArgs,
+    },
+    /// Verify a revealed fairness ledger saved from `GET /fair/:id`
+    Verify {
+        /// JSON file holding the ledger
+        file: String,
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Draw samples and run statistical quality tests on them
+    Selftest(SelftestArgs),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// One value per line
+    #[default]
+    Plain,
+    /// One JSON document per line
+    Json,
+    Csv,
+}
+
+#[derive(Args, Clone)]
+pub struct SeedArgs {
+    #[arg(long)]
+    pub seed: Option<u64>,
+    #[arg(long, default_value_t = 0)]
+    pub stream: u64,
+    #[arg(long, default_value_t = 0)]
+    pub offset: u64,
+}
+
+impl SeedArgs {
+    fn parameters(&self) -> SeedParameters {
+        SeedParameters {
+            seed: self.seed,
+            stream: self.stream,
+            offset: self.offset,
+        }
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct GenerateArgs {
+    #[arg(long, default_value_t = 1)]
+    pub count: u64,
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+    #[command(flatten)]
+    pub seed: SeedArgs,
+}
+
+#[derive(Args)]
+pub struct DiceArgs {
+    pub expr: String,
+    #[arg(long, default_value_t = 1)]
+    pub count: u64,
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+    #[command(flatten)]
+    pub seed: SeedArgs,
+}
+
+#[derive(Args)]
+pub struct TokenArgs {
+    #[arg(long, value_enum, default_value_t)]
+    pub kind: TokenKind,
+    #[arg(long)]
+    pub length: Option<usize>,
+    /// Comma-separated classes: lower, upper, digit, symbol
+    #[arg(long)]
+    pub charset: Option<String>,
+    /// Comma-separated classes that must appear at least once
+    #[arg(long)]
+    pub require: Option<String>,
+    #[arg(long)]
+    pub exclude_ambiguous: bool,
+    #[arg(long, default_value_t = 1)]
+    pub count: u64,
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct ListArgs {
+    /// Items; read one per line from stdin when none are given
+    pub items: Vec<String>,
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+    #[command(flatten)]
+    pub seed: SeedArgs,
+}
+
+/// What to generate; shared by every command that draws values.
+#[derive(Subcommand, Clone)]
+pub enum SamplerCommand {
+    /// Uniform integers in [start, end)
+    Int(IntArgs),
+    /// Uniform floats in [start, end)
+    Float(FloatArgs),
+    /// Booleans that are true with probability p
+    Bool(BoolArgs),
+    /// Non-uniform distributions
+    Dist(DistArgs),
+}
+
+#[derive(Args, Clone)]
+pub struct IntArgs {
+    #[arg(long, allow_negative_numbers = true)]
+    pub start: i64,
+    #[arg(long, allow_negative_numbers = true)]
+    pub end: i64,
+    /// Include `end` in the range
+    #[arg(long)]
+    pub inclusive: bool,
+}
+
+#[derive(Args, Clone)]
+pub struct FloatArgs {
+    #[arg(long, allow_negative_numbers = true)]
+    pub start: f64,
+    #[arg(long, allow_negative_numbers = true)]
+    pub end: f64,
+    /// Include `end` in the range
+    #[arg(long)]
+    pub inclusive: bool,
+}
+
+#[derive(Args, Clone)]
+pub struct BoolArgs {
+    #[arg(long, default_value_t = 0.5)]
+    pub p: f64,
+}
+
+#[derive(Args, Clone)]
+pub struct DistArgs {
+    #[arg(long, value_enum)]
+    pub dist: DistKind,
+    #[arg(long, allow_negative_numbers = true)]
+    pub mean: Option<f64>,
+    #[arg(long, allow_negative_numbers = true)]
+    pub stddev: Option<f64>,
+    #[arg(long)]
+    pub lambda: Option<f64>,
+    #[arg(long)]
+    pub n: Option<u64>,
+    #[arg(long)]
+    pub p: Option<f64>,
+    /// Comma-separated category weights
+    #[arg(long)]
+    pub weights: Option<String>,
+}
+
+impl SamplerCommand {
+    pub fn sampler(&self) -> Result<Sampler, ParamError> {
+        match self {
+            SamplerCommand::Int(args) => Sampler::int(args.start, args.end, args.inclusive),
+            SamplerCommand::Float(args) => Sampler::float(args.start, args.end, args.inclusive),
+            SamplerCommand::Bool(args) => Sampler::bool(args.p),
+            SamplerCommand::Dist(args) => Sampler::dist(&DistParameters {
+                dist: args.dist,
+                mean: args.mean,
+                stddev: args.stddev,
+                lambda: args.lambda,
+                n: args.n,
+                p: args.p,
+                weights: args.weights.clone(),
+            }),
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct SelftestArgs {
+    #[command(subcommand)]
+    pub sampler: SamplerCommand,
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub generator: GeneratorKind,
+    #[arg(long, global = true, default_value_t = 10_000)]
+    pub samples: usize,
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+    /// Significance level below which a test fails
+    #[arg(long, global = true, default_value_t = 0.01)]
+    pub alpha: f64,
+    /// Print the report as JSON
+    #[arg(long, global = true)]
+    pub json: bool,
+}
+
+/// Parameters that are positional arguments on the command line rather than
+/// `--flags`.
+const POSITIONAL: &[&str] = &["expr", "items"];
+
+/// Report a parameter error the way clap reports usage errors, naming the
+/// argument as it appears in `--help`.
+fn usage_error(err: ParamError) -> ExitCode {
+    let argument = if POSITIONAL.contains(&err.parameter) {
+        format!("<{}>", err.parameter.to_uppercase())
+    } else {
+        format!("--{}", err.parameter.replace('_', "-"))
+    };
+    eprintln!("error: {argument}: {}", err.message);
+    ExitCode::from(2)
+}
+
+/// Run a command that does not start the server.
+pub fn run(command: OfflineCommand) -> ExitCode {
+    let result = match command {
+        OfflineCommand::Int { args, output } => generate(SamplerCommand::Int(args), output),
+        OfflineCommand::Float { args, output } => generate(SamplerCommand::Float(args), output),
+        OfflineCommand::Bool { args, output } => generate(SamplerCommand::Bool(args), output),
+        OfflineCommand::Dist { args, output } => generate(SamplerCommand::Dist(args), output),
+        OfflineCommand::Dice(args) => roll_dice(args),
+        OfflineCommand::Token(args) => tokens(args),
+        OfflineCommand::Shuffle(list) => {
+            let items = read_items(&list);
+            lists::shuffle(&items, &list.seed.parameters()).map(|r| print_list(r, list.format))
+        }
+        OfflineCommand::Sample { k, list } => {
+            let items = read_items(&list);
+            lists::sample(&items, k, &list.seed.parameters()).map(|r| print_list(r, list.format))
+        }
+        OfflineCommand::Pick {
+            weights,
+            count,
+            list,
+        } => {
+            let items = read_items(&list);
+            weights
+                .as_deref()
+                .map(crate::distribution::parse_weights)
+                .transpose()
+                .and_then(|weights| {
+                    lists::pick(&items, weights.as_deref(), count, &list.seed.parameters())
+                })
+                .map(|r| print_list(r, list.format))
+        }
+        OfflineCommand::Verify { file, format } => return verify(&file, format),
+        OfflineCommand::Selftest(args) => return selftest(args),
+    };
+    match result {
+        Ok(Ok(())) => ExitCode::SUCCESS,
+        // The reader went away (e.g. `| head`), which is not an error.
+        Ok(Err(err)) if err.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Ok(Err(err)) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+        Err(err) => usage_error(err),
+    }
+}
+
+/// Print where the output sits in its sequence so a run can be replayed.
+fn print_sequence(sequence: SequenceInfo) {
+    eprintln!(
+        "seed: {} stream: {} index: {}",
+        sequence.seed, sequence.stream, sequence.index
+    );
+}
+
+fn generate(sampler: SamplerCommand, args: GenerateArgs) -> Result<io::Result<()>, ParamError> {
+    let sampler = sampler.sampler()?;
+    let source = Source::from_parameters(&args.seed.parameters(), |rng| sampler.sample(rng))?;
+    print_sequence(source.info());
+    Ok(with_stdout(|out| {
+        if args.format == OutputFormat::Csv {
+            out.write_all(CSV_HEADER.as_bytes())?;
+        }
+        for (index, value) in source.values(sampler, args.count) {
+            match args.format {
+                OutputFormat::Plain => writeln!(out, "{value}")?,
+                OutputFormat::Json => out.write_all(ndjson_record(index, value).as_bytes())?,
+                OutputFormat::Csv => out.write_all(csv_record(index, value).as_bytes())?,
+            }
+        }
+        Ok(())
+    }))
+}
+
+fn roll_dice(args: DiceArgs) -> Result<io::Result<()>, ParamError> {
+    let expr = match Expr::parse(&args.expr) {
+        Ok(expr) => expr,
+        Err(err) => {
+            eprintln!("{}", args.expr);
+            eprintln!("{:>width$}", "^", width = err.column);
+            return Err(ParamError::new("expr", err.to_string()));
+        }
+    };
+    // Same steps as `/dice`, continued for `count` results of the sequence.
+    let mut source =
+        Source::from_parameters_with_cost(&args.seed.parameters(), expr.dice_count(), |rng| {
+            expr.roll(rng)
+        })?;
+    print_sequence(source.info());
+    let rolls = (0..args.count).map(|_| {
+        let sequence = source.info();
+        DiceResponse {
+            expr: expr.to_string(),
+            roll: source.next_with(|rng| expr.roll(rng)),
+            sequence,
+        }
+    });
+    Ok(with_stdout(|out| {
+        if args.format == OutputFormat::Csv {
+            writeln!(out, "index,expr,total")?;
+        }
+        for roll in rolls {
+            match args.format {
+                OutputFormat::Plain => writeln!(out, "{}", roll.roll.total)?,
+                OutputFormat::Json => print_json(out, &roll)?,
+                OutputFormat::Csv => writeln!(
+                    out,
+                    "{},\"{}\",{}",
+                    roll.sequence.index, roll.expr, roll.roll.total
+                )?,
+            }
+        }
+        Ok(())
+    }))
+}
+
+fn tokens(args: TokenArgs) -> Result<io::Result<()>, ParamError> {
+    let spec = TokenSpec::new(&TokenParameters {
+        kind: args.kind,
+        length: args.length,
+        charset: args.charset,
+        require: args.require,
+        exclude_ambiguous: args.exclude_ambiguous,
+    })?;
+    Ok(with_stdout(|out| {
+        if args.format == OutputFormat::Csv {
+            writeln!(out, "value,entropy_bits")?;
+        }
+        for _ in 0..args.count {
+            let token = spec.generate();
+            match args.format {
+                OutputFormat::Plain => writeln!(out, "{}", token.value)?,
+                OutputFormat::Json => print_json(out, &token)?,
+                OutputFormat::Csv => writeln!(
+                    out,
+                    "\"{}\",{}",
+                    token.value.replace('"', "\"\""),
+                    token.entropy_bits
+                )?,
+            }
+        }
+        Ok(())
+    }))
+}
+
+fn read_items(list: &ListArgs) -> Vec<JsonValue> {
+    if !list.items.is_empty() {
+        return list.items.iter().cloned().map(JsonValue::String).collect();
+    }
+    io::stdin()
+        .lock()
+        .lines()
+        .map_while(Result::ok)
+        .filter(|line| !line.is_empty())
+        .map(JsonValue::String)
+        .collect()
+}
+
+fn print_list(response: lists::ListResponse, format: OutputFormat) -> io::Result<()> {
+    print_sequence(response.sequence);
+    with_stdout(|out| {
+        if format == OutputFormat::Json {
+            return print_json(out, &response);
+        }
+        if format == OutputFormat::Csv {
+            writeln!(out, "index,item")?;
+        }
+        for (index, item) in response.indices.iter().zip(&response.items) {
+            let item = item.as_str().unwrap_or_default();
+            match format {
+                OutputFormat::Csv => writeln!(out, "{index},\"{}\"", item.replace('"', "\"\""))?,
+                _ => writeln!(out, "{item}")?,
+            }
+        }
+        Ok(())
+    })
+}
+
+fn with_stdout(f: impl FnOnce(&mut dyn Write) -> io::Result<()>) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    f(&mut out)?;
+    out.flush()
+}
+
+fn print_json(out: &mut dyn Write, value: &impl Serialize) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, value)?;
+    writeln!(out)
+}
+
+/// `verify`: exits with 1 when the ledger does not check out.
+fn verify(file: &str, format: OutputFormat) -> ExitCode {
+    let log: LedgerView = match fs::read_to_string(file)
+        .map_err(|e| e.to_string())
+        .and_then(|text| serde_json::from_str(&text).map_err(|e| e.to_string()))
+    {
+        Ok(log) => log,
+        Err(err) => {
+            eprintln!("error: {file}: {err}");
+            return ExitCode::from(2);
+        }
+    };
+    let verification = fairness::verify(&log);
+    if format == OutputFormat::Json {
+        let _ = print_json(&mut io::stdout().lock(), &verification);
+    } else {
+        for issue in &verification.issues {
+            match issue.index {
+                Some(index) => println!("entry {index}: {}", issue.message),
+                None => println!("{}", issue.message),
+            }
+        }
+        println!(
+            "{} entries checked: {}",
+            verification.entries_checked,
+            if verification.valid {
+                "VALID"
+            } else {
+                "INVALID"
+            }
+        );
+    }
+    if verification.valid {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// `selftest`: exits with 1 when any test fails.
+fn selftest(args: SelftestArgs) -> ExitCode {
+    let params = SelftestParameters {
+        generator: args.generator,
+        samples: args.samples,
+        seed: args.seed,
+        alpha: args.alpha,
+    };
+    let sampler = match args
+        .sampler
+        .sampler()
+        .and_then(|s| params.validate().map(|_| s))
+    {
+        Ok(sampler) => sampler,
+        Err(err) => return usage_error(err),
+    };
+    let report = selftest::run(&sampler, &params);
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("report serializes to JSON")
+        );
+    } else {
+        let seed = report.seed.map_or("-".to_string(), |s| s.to_string());
+        println!(
+            "generator: {}  samples: {}  seed: {}  alpha: {}",
+            args.generator
+                .to_possible_value()
+                .expect("no skipped variants")
+                .get_name(),
+            report.samples,
+            seed,
+            report.alpha
+        );
+        for test in &report.tests {
+            match (test.statistic, test.p_value, test.passed) {
+                (Some(statistic), Some(p_value), Some(passed)) => println!(
+                    "{:<20} {:>14.6} p={:<10.6} {}",
+                    test.name,
+                    statistic,
+                    p_value,
+                    if passed { "PASS" } else { "FAIL" }
+                ),
+                _ => println!(
+                    "{:<20} skipped: {}",
+                    test.name,
+                    test.skipped.unwrap_or_default()
+                ),
+            }
+        }
+        println!("{}", if report.passed { "PASS" } else { "FAIL" });
+    }
+    if report.passed {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
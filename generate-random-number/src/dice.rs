@@ -0,0 +1,519 @@
+//! Dice notation such as `3d6+2`, `4d6kh3` or `2d20kl1 + 1d4`.
+//!
+//! ```text
+//! expr  := term (('+' | '-') term)*
+//! term  := dice | number
+//! dice  := number? 'd' number (('kh' | 'kl') number)?
+//! ```
+
+use std::fmt;
+
+use axum::{
+    extract::{rejection::QueryRejection, Path, Query},
+    Json,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+use crate::generator::ParamError;
+use crate::seed::{SeedParameters, SequenceInfo, Source};
+
+pub const MAX_TERMS: usize = 100;
+pub const MAX_DICE: u32 = 1000;
+pub const MAX_SIDES: u32 = 1_000_000;
+
+/// A syntax or limit error, with the 1-based column it refers to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Sign {
+    #[serde(rename = "+")]
+    Plus,
+    #[serde(rename = "-")]
+    Minus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Keep {
+    Highest(u32),
+    Lowest(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Dice {
+        count: u32,
+        sides: u32,
+        keep: Option<Keep>,
+    },
+    Constant(i64),
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::Dice { count, sides, keep } => {
+                write!(f, "{count}d{sides}")?;
+                match keep {
+                    Some(Keep::Highest(n)) => write!(f, "kh{n}"),
+                    Some(Keep::Lowest(n)) => write!(f, "kl{n}"),
+                    None => Ok(()),
+                }
+            }
+            Term::Constant(n) => write!(f, "{n}"),
+        }
+    }
+}
+
+/// A parsed dice expression: a signed sum of terms.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub terms: Vec<(Sign, Term)>,
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (sign, term)) in self.terms.iter().enumerate() {
+            match (i, sign) {
+                (0, Sign::Plus) => {}
+                (0, Sign::Minus) => write!(f, "-")?,
+                (_, Sign::Plus) => write!(f, " + ")?,
+                (_, Sign::Minus) => write!(f, " - ")?,
+            }
+            write!(f, "{term}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TermRoll {
+    pub sign: Sign,
+    pub term: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rolls: Option<Vec<u32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kept: Option<Vec<u32>>,
+    pub subtotal: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Roll {
+    pub terms: Vec<TermRoll>,
+    pub total: i64,
+}
+
+impl Expr {
+    pub fn parse(input: &str) -> Result<Expr, ParseError> {
+        Parser::new(input).parse()
+    }
+
+    /// Number of dice thrown by one roll.
+    pub fn dice_count(&self) -> u64 {
+        self.terms
+            .iter()
+            .map(|(_, term)| match term {
+                Term::Dice { count, .. } => u64::from(*count),
+                Term::Constant(_) => 0,
+            })
+            .sum()
+    }
+
+    pub fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> Roll {
+        let mut total = 0i64;
+        let terms = self
+            .terms
+            .iter()
+            .map(|(sign, term)| {
+                let roll = match *term {
+                    Term::Constant(n) => TermRoll {
+                        sign: *sign,
+                        term: term.to_string(),
+                        rolls: None,
+                        kept: None,
+                        subtotal: n,
+                    },
+                    Term::Dice { count, sides, keep } => {
+                        let rolls: Vec<u32> =
+                            (0..count).map(|_| rng.gen_range(1..=sides)).collect();
+                        let kept = keep_dice(&rolls, keep);
+                        TermRoll {
+                            sign: *sign,
+                            term: term.to_string(),
+                            subtotal: kept.iter().map(|&v| i64::from(v)).sum(),
+                            rolls: Some(rolls),
+                            kept: Some(kept),
+                        }
+                    }
+                };
+                total = match sign {
+                    Sign::Plus => total.saturating_add(roll.subtotal),
+                    Sign::Minus => total.saturating_sub(roll.subtotal),
+                };
+                roll
+            })
+            .collect();
+        Roll { terms, total }
+    }
+}
+
+/// The dice that count towards the subtotal, in the order they were rolled.
+fn keep_dice(rolls: &[u32], keep: Option<Keep>) -> Vec<u32> {
+    let (n, highest) = match keep {
+        None => return rolls.to_vec(),
+        Some(Keep::Highest(n)) => (n as usize, true),
+        Some(Keep::Lowest(n)) => (n as usize, false),
+    };
+    let mut order: Vec<usize> = (0..rolls.len()).collect();
+    order.sort_by_key(|&i| rolls[i]);
+    if highest {
+        order.reverse();
+    }
+    let mut kept_indices = order[..n.min(rolls.len())].to_vec();
+    kept_indices.sort_unstable();
+    kept_indices.into_iter().map(|i| rolls[i]).collect()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(input: &str) -> Self {
+        Parser {
+            chars: input.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn error<T>(&self, column: usize, message: impl Into<String>) -> Result<T, ParseError> {
+        Err(ParseError {
+            column: column + 1,
+            message: message.into(),
+        })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek().map(|p| p.to_ascii_lowercase()) == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse(mut self) -> Result<Expr, ParseError> {
+        let mut terms = Vec::new();
+        self.skip_whitespace();
+        if self.peek().is_none() {
+            return self.error(self.pos, "expected a dice expression");
+        }
+        let mut sign = Sign::Plus;
+        loop {
+            let start = self.pos;
+            terms.push((sign, self.term()?));
+            if terms.len() > MAX_TERMS {
+                return self.error(start, format!("at most {MAX_TERMS} terms are allowed"));
+            }
+            self.skip_whitespace();
+            sign = match self.peek() {
+                None => break,
+                Some('+') => Sign::Plus,
+                Some('-') => Sign::Minus,
+                Some(c) => {
+                    return self.error(self.pos, format!("expected `+` or `-`, found `{c}`"))
+                }
+            };
+            self.pos += 1;
+            self.skip_whitespace();
+        }
+        Ok(Expr { terms })
+    }
+
+    fn term(&mut self) -> Result<Term, ParseError> {
+        let start = self.pos;
+        let count = self.number()?;
+        if !self.eat('d') {
+            return match count {
+                Some(n) => Ok(Term::Constant(n as i64)),
+                None => match self.peek() {
+                    Some(c) => self.error(start, format!("expected a number or dice, found `{c}`")),
+                    None => self.error(start, "expected a number or dice"),
+                },
+            };
+        }
+        let count = count.unwrap_or(1);
+        if count == 0 || count > u64::from(MAX_DICE) {
+            return self.error(
+                start,
+                format!("dice count must be between 1 and {MAX_DICE}"),
+            );
+        }
+        let sides_at = self.pos;
+        let sides = match self.number()? {
+            Some(n) if (1..=u64::from(MAX_SIDES)).contains(&n) => n as u32,
+            Some(_) => {
+                return self.error(sides_at, format!("sides must be between 1 and {MAX_SIDES}"))
+            }
+            None => return self.error(sides_at, "expected the number of sides after `d`"),
+        };
+        let keep = if self.eat('k') {
+            let highest = if self.eat('h') {
+                true
+            } else if self.eat('l') {
+                false
+            } else {
+                return self.error(self.pos, "expected `h` or `l` after `k`");
+            };
+            let n_at = self.pos;
+            let n = match self.number()? {
+                Some(n) if n <= count => n as u32,
+                Some(_) => {
+                    return self.error(n_at, format!("cannot keep more than {count} dice"));
+                }
+                None => return self.error(n_at, "expected how many dice to keep"),
+            };
+            Some(if highest {
+                Keep::Highest(n)
+            } else {
+                Keep::Lowest(n)
+            })
+        } else {
+            None
+        };
+        if self.peek().is_some_and(|c| c.is_alphanumeric()) {
+            return self.error(self.pos, format!("unexpected `{}`", self.chars[self.pos]));
+        }
+        Ok(Term::Dice {
+            count: count as u32,
+            sides,
+            keep,
+        })
+    }
+
+    fn number(&mut self) -> Result<Option<u64>, ParseError> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Ok(None);
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        match digits.parse::<u64>() {
+            Ok(n) if n <= i64::MAX as u64 => Ok(Some(n)),
+            _ => self.error(start, "number is too large"),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DiceParameters {
+    expr: String,
+}
+
+#[derive(Serialize)]
+pub struct DiceResponse {
+    pub expr: String,
+    #[serde(flatten)]
+    pub roll: Roll,
+    #[serde(flatten)]
+    pub sequence: SequenceInfo,
+}
+
+/// `GET /dice?expr=4d6kh3%2B2` (note that `+` must be percent-encoded in a query string).
+pub async fn dice_handler(
+    params: Result<Query<DiceParameters>, QueryRejection>,
+    seed: Result<Query<SeedParameters>, QueryRejection>,
+) -> Result<Json<DiceResponse>, ApiError> {
+    let Query(params) = params?;
+    roll_expr(&params.expr, seed)
+}
+
+/// `GET /dice/4d6kh3+2`, convenient for bots and the command line.
+pub async fn dice_path_handler(
+    Path(expr): Path<String>,
+    seed: Result<Query<SeedParameters>, QueryRejection>,
+) -> Result<Json<DiceResponse>, ApiError> {
+    roll_expr(&expr, seed)
+}
+
+fn roll_expr(
+    input: &str,
+    seed: Result<Query<SeedParameters>, QueryRejection>,
+) -> Result<Json<DiceResponse>, ApiError> {
+    let Query(seed) = seed?;
+    let expr = Expr::parse(input)?;
+    Ok(Json(roll(&expr, &seed)?))
+}
+
+/// Roll a parsed expression from a seeded source.
+pub fn roll(expr: &Expr, seed: &SeedParameters) -> Result<DiceResponse, ParamError> {
+    let mut source =
+        Source::from_parameters_with_cost(seed, expr.dice_count(), |rng| expr.roll(rng))?;
+    let sequence = source.info();
+    let roll = source.next_with(|rng| expr.roll(rng));
+    Ok(DiceResponse {
+        expr: expr.to_string(),
+        roll,
+        sequence,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    fn dice(count: u32, sides: u32, keep: Option<Keep>) -> Term {
+        Term::Dice { count, sides, keep }
+    }
+
+    fn error(input: &str) -> (usize, String) {
+        let err = Expr::parse(input).unwrap_err();
+        (err.column, err.message)
+    }
+
+    #[test]
+    fn parses_terms_and_modifiers() {
+        let expr = Expr::parse(" 4d6kh3 - D20 + 2D20KL1 + 5 ").unwrap();
+        assert_eq!(
+            expr.terms,
+            [
+                (Sign::Plus, dice(4, 6, Some(Keep::Highest(3)))),
+                (Sign::Minus, dice(1, 20, None)),
+                (Sign::Plus, dice(2, 20, Some(Keep::Lowest(1)))),
+                (Sign::Plus, Term::Constant(5)),
+            ]
+        );
+        assert_eq!(expr.to_string(), "4d6kh3 - 1d20 + 2d20kl1 + 5");
+        assert_eq!(Expr::parse(&expr.to_string()).unwrap(), expr);
+        assert_eq!(expr.dice_count(), 7);
+    }
+
+    #[test]
+    fn terms_are_summed_left_to_right() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        // `10 - (2 + 3)` would be 5.
+        assert_eq!(Expr::parse("10 - 2 + 3").unwrap().roll(&mut rng).total, 11);
+        assert_eq!(Expr::parse("1 - 5").unwrap().roll(&mut rng).total, -4);
+        let roll = Expr::parse("3d1 - 2d1 + 1").unwrap().roll(&mut rng);
+        assert_eq!(roll.total, 2);
+        assert_eq!(roll.terms[1].subtotal, 2);
+    }
+
+    #[test]
+    fn keep_modifiers_keep_roll_order() {
+        let rolls = [3, 6, 1, 6, 2];
+        assert_eq!(keep_dice(&rolls, None), rolls);
+        assert_eq!(keep_dice(&rolls, Some(Keep::Highest(3))), [3, 6, 6]);
+        assert_eq!(keep_dice(&rolls, Some(Keep::Lowest(2))), [1, 2]);
+        assert_eq!(keep_dice(&rolls, Some(Keep::Highest(0))), [0; 0]);
+        assert_eq!(keep_dice(&rolls, Some(Keep::Lowest(5))), rolls);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let expr = Expr::parse("4d6kh3").unwrap();
+        for _ in 0..100 {
+            let term = &expr.roll(&mut rng).terms[0];
+            let mut rolls = term.rolls.clone().unwrap();
+            let kept = term.kept.as_ref().unwrap();
+            assert_eq!(kept.len(), 3);
+            assert_eq!(
+                term.subtotal,
+                kept.iter().map(|&v| i64::from(v)).sum::<i64>()
+            );
+            rolls.sort_unstable();
+            assert_eq!(
+                term.subtotal,
+                rolls[1..].iter().map(|&v| i64::from(v)).sum::<i64>()
+            );
+        }
+    }
+
+    #[test]
+    fn limits_dice_sides_and_terms() {
+        assert!(Expr::parse("1000d1000000").is_ok());
+        assert_eq!(
+            error("1001d6"),
+            (1, format!("dice count must be between 1 and {MAX_DICE}"))
+        );
+        assert_eq!(error("2 + 0d6").0, 5);
+        assert_eq!(
+            error("1 + d1000001"),
+            (6, format!("sides must be between 1 and {MAX_SIDES}"))
+        );
+
+        let at_limit = vec!["1"; MAX_TERMS].join("+");
+        assert!(Expr::parse(&at_limit).is_ok());
+        let over = vec!["1"; MAX_TERMS + 1].join("+");
+        // The 101st term starts after 100 `1+` pairs.
+        assert_eq!(
+            error(&over),
+            (
+                2 * MAX_TERMS + 1,
+                format!("at most {MAX_TERMS} terms are allowed")
+            )
+        );
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_column() {
+        assert_eq!(error(""), (1, "expected a dice expression".to_string()));
+        assert_eq!(error("   ").0, 4);
+        assert_eq!(
+            error("-2"),
+            (1, "expected a number or dice, found `-`".to_string())
+        );
+        assert_eq!(error("2d6+"), (5, "expected a number or dice".to_string()));
+        assert_eq!(
+            error("3d"),
+            (3, "expected the number of sides after `d`".to_string())
+        );
+        assert_eq!(error("3d0").0, 3);
+        assert_eq!(
+            error("2d6kx"),
+            (5, "expected `h` or `l` after `k`".to_string())
+        );
+        assert_eq!(
+            error("2d6kh3"),
+            (6, "cannot keep more than 2 dice".to_string())
+        );
+        assert_eq!(
+            error("2d6kh"),
+            (6, "expected how many dice to keep".to_string())
+        );
+        assert_eq!(
+            error("2d6 * 2"),
+            (5, "expected `+` or `-`, found `*`".to_string())
+        );
+        assert_eq!(error("2d6x"), (4, "unexpected `x`".to_string()));
+        assert_eq!(
+            error("1 + 99999999999999999999"),
+            (5, "number is too large".to_string())
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use clap::ValueEnum;
+use rand::Rng;
+use rand_distr::{Binomial, Distribution as _, Exp, LogNormal, Normal, Poisson, WeightedIndex};
+use serde::Deserialize;
+
+use crate::generator::{ParamError, Value};
+
+/// Largest Poisson `lambda`; below 2^53 every sample is still an exact integer
+/// in an `f64` and far from overflowing an `i64`.
+pub const MAX_POISSON_LAMBDA: f64 = 1e15;
+
+#[derive(Debug, Clone, Copy, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DistKind {
+    Normal,
+    #[value(name = "lognormal")]
+    LogNormal,
+    Exponential,
+    Poisson,
+    Binomial,
+    Categorical,
+}
+
+impl DistKind {
+    fn name(self) -> &'static str {
+        match self {
+            DistKind::Normal => "normal",
+            DistKind::LogNormal => "lognormal",
+            DistKind::Exponential => "exponential",
+            DistKind::Poisson => "poisson",
+            DistKind::Binomial => "binomial",
+            DistKind::Categorical => "categorical",
+        }
+    }
+}
+
+/// Raw, unvalidated distribution parameters as they arrive from a query string
+/// or the command line. `weights` is a comma-separated list.
+#[derive(Debug, Deserialize)]
+pub struct DistParameters {
+    pub dist: DistKind,
+    pub mean: Option<f64>,
+    pub stddev: Option<f64>,
+    pub lambda: Option<f64>,
+    pub n: Option<u64>,
+    pub p: Option<f64>,
+    pub weights: Option<String>,
+}
+
+/// A validated non-uniform distribution.
+#[derive(Debug, Clone)]
+pub enum Distribution {
+    Normal {
+        mean: f64,
+        stddev: f64,
+        dist: Normal<f64>,
+    },
+    LogNormal {
+        mean: f64,
+        stddev: f64,
+        dist: LogNormal<f64>,
+    },
+    Exponential {
+        lambda: f64,
+        dist: Exp<f64>,
+    },
+    Poisson {
+        lambda: f64,
+        dist: Poisson<f64>,
+    },
+    Binomial {
+        n: u64,
+        p: f64,
+        dist: Binomial,
+    },
+    Categorical {
+        weights: Vec<f64>,
+        dist: WeightedIndex<f64>,
+    },
+}
+
+impl Distribution {
+    pub fn new(params: &DistParameters) -> Result<Self, ParamError> {
+        let kind = params.dist;
+        let allowed: &[&str] = match kind {
+            DistKind::Normal | DistKind::LogNormal => &["mean", "stddev"],
+            DistKind::Exponential | DistKind::Poisson => &["lambda"],
+            DistKind::Binomial => &["n", "p"],
+            DistKind::Categorical => &["weights"],
+        };
+        let given = [
+            ("mean", params.mean.is_some()),
+            ("stddev", params.stddev.is_some()),
+            ("lambda", params.lambda.is_some()),
+            ("n", params.n.is_some()),
+            ("p", params.p.is_some()),
+            ("weights", params.weights.is_some()),
+        ];
+        if let Some(&(name, _)) = given
+            .iter()
+            .find(|(name, given)| *given && !allowed.contains(name))
+        {
+            return Err(ParamError::new(
+                name,
+                format!("is not a parameter of the {} distribution", kind.name()),
+            ));
+        }
+
+        Ok(match kind {
+            DistKind::Normal | DistKind::LogNormal => {
+                let mean = finite("mean", required("mean", params.mean, kind)?)?;
+                let stddev = finite("stddev", required("stddev", params.stddev, kind)?)?;
+                if stddev < 0.0 {
+                    return Err(ParamError::new("stddev", "must not be negative"));
+                }
+                match kind {
+                    DistKind::Normal => Distribution::Normal {
+                        mean,
+                        stddev,
+                        dist: Normal::new(mean, stddev)
+                            .map_err(|e| ParamError::new("stddev", e.to_string()))?,
+                    },
+                    _ => Distribution::LogNormal {
+                        mean,
+                        stddev,
+                        dist: LogNormal::new(mean, stddev)
+                            .map_err(|e| ParamError::new("stddev", e.to_string()))?,
+                    },
+                }
+            }
+            DistKind::Exponential => {
+                let lambda = positive("lambda", required("lambda", params.lambda, kind)?)?;
+                Distribution::Exponential {
+                    lambda,
+                    dist: Exp::new(lambda).map_err(|e| ParamError::new("lambda", e.to_string()))?,
+                }
+            }
+            DistKind::Poisson => {
+                let lambda = positive("lambda", required("lambda", params.lambda, kind)?)?;
+                if lambda > MAX_POISSON_LAMBDA {
+                    return Err(ParamError::new(
+                        "lambda",
+                        format!("must not exceed {MAX_POISSON_LAMBDA:e}"),
+                    ));
+                }
+                Distribution::Poisson {
+                    lambda,
+                    dist: Poisson::new(lambda)
+                        .map_err(|e| ParamError::new("lambda", e.to_string()))?,
+                }
+            }
+            DistKind::Binomial => {
+                let n = required("n", params.n, kind)?;
+                let p = required("p", params.p, kind)?;
+                if !(0.0..=1.0).contains(&p) {
+                    return Err(ParamError::new("p", "must be between 0 and 1"));
+                }
+                if n > i64::MAX as u64 {
+                    return Err(ParamError::new("n", "is too large"));
+                }
+                Distribution::Binomial {
+                    n,
+                    p,
+                    dist: Binomial::new(n, p).map_err(|e| ParamError::new("p", e.to_string()))?,
+                }
+            }
+            DistKind::Categorical => {
+                let weights = parse_weights(required("weights", params.weights.as_deref(), kind)?)?;
+                Distribution::Categorical {
+                    dist: WeightedIndex::new(&weights)
+                        .map_err(|e| ParamError::new("weights", e.to_string()))?,
+                    weights,
+                }
+            }
+        })
+    }
+
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Value {
+        match self {
+            Distribution::Normal { dist, .. } => Value::Float(dist.sample(rng)),
+            Distribution::LogNormal { dist, .. } => Value::Float(dist.sample(rng)),
+            Distribution::Exponential { dist, .. } => Value::Float(dist.sample(rng)),
+            Distribution::Poisson { dist, .. } => Value::Int(dist.sample(rng) as i64),
+            Distribution::Binomial { dist, .. } => Value::Int(dist.sample(rng) as i64),
+            Distribution::Categorical { dist, .. } => Value::Int(dist.sample(rng) as i64),
+        }
+    }
+}
+
+/// Parse a comma-separated list of non-negative weights, at least one positive.
+pub fn parse_weights(raw: &str) -> Result<Vec<f64>, ParamError> {
+    let weights = raw
+        .split(',')
+        .map(|w| {
+            w.trim()
+                .parse::<f64>()
+                .map_err(|_| ParamError::new("weights", format!("`{}` is not a number", w.trim())))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    check_weights(&weights)?;
+    Ok(weights)
+}
+
+pub fn check_weights(weights: &[f64]) -> Result<(), ParamError> {
+    if weights.is_empty() {
+        return Err(ParamError::new("weights", "must not be empty"));
+    }
+    if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+        return Err(ParamError::new(
+            "weights",
+            "must be finite and not negative",
+        ));
+    }
+    if weights.iter().all(|w| *w == 0.0) {
+        return Err(ParamError::new("weights", "must contain a positive weight"));
+    }
+    // `WeightedIndex` panics when the total overflows.
+    if !weights.iter().sum::<f64>().is_finite() {
+        return Err(ParamError::new("weights", "must add up to a finite number"));
+    }
+    Ok(())
+}
+
+fn required<T>(name: &'static str, value: Option<T>, kind: DistKind) -> Result<T, ParamError> {
+    value.ok_or_else(|| {
+        ParamError::new(
+            name,
+            format!("is required for the {} distribution", kind.name()),
+        )
+    })
+}
+
+fn finite(name: &'static str, value: f64) -> Result<f64, ParamError> {
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(ParamError::new(name, "must be a finite number"))
+    }
+}
+
+fn positive(name: &'static str, value: f64) -> Result<f64, ParamError> {
+    if finite(name, value)? > 0.0 {
+        Ok(value)
+    } else {
+        Err(ParamError::new(name, "must be greater than 0"))
+    }
+}
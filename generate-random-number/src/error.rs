@@ -0,0 +1,97 @@
+use axum::{
+    extract::rejection::{JsonRejection, QueryRejection},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use server_core::error::ErrorResponse;
+
+use crate::dice::ParseError;
+use crate::generator::ParamError;
+
+/// Error returned by the HTTP handlers, always rendered as the shared JSON
+/// error body.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+    parameter: Option<&'static str>,
+    column: Option<usize>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            code,
+            message: message.into(),
+            parameter: None,
+            column: None,
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        ApiError::new(StatusCode::BAD_REQUEST, "bad_request", message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        ApiError::new(StatusCode::NOT_FOUND, "not_found", message)
+    }
+
+    pub fn conflict(code: &'static str, message: impl Into<String>) -> Self {
+        ApiError::new(StatusCode::CONFLICT, code, message)
+    }
+}
+
+impl From<ParamError> for ApiError {
+    fn from(err: ParamError) -> Self {
+        ApiError {
+            status: StatusCode::BAD_REQUEST,
+            code: "invalid_parameter",
+            message: err.message,
+            parameter: Some(err.parameter),
+            column: None,
+        }
+    }
+}
+
+impl From<ParseError> for ApiError {
+    fn from(err: ParseError) -> Self {
+        ApiError {
+            status: StatusCode::BAD_REQUEST,
+            code: "parse_error",
+            message: err.message,
+            parameter: None,
+            column: Some(err.column),
+        }
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        ApiError::bad_request(rejection.body_text())
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        ApiError {
+            status: rejection.status(),
+            code: "invalid_body",
+            ..ApiError::bad_request(rejection.body_text())
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut response = ErrorResponse::new(self.status, self.code, self.message);
+        if let Some(parameter) = self.parameter {
+            response = response.with_detail("parameter", parameter);
+        }
+        if let Some(column) = self.column {
+            response = response.with_detail("column", column);
+        }
+        response.into_response()
+    }
+}
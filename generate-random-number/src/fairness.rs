@@ -0,0 +1,460 @@
+//! Commit–reveal fairness log.
+//!
+//! A ledger is created with a secret server seed and only its SHA-256
+//! commitment is published. Every draw is appended to a hash chain that starts
+//! at the commitment. Revealing the seed closes the ledger, after which anyone
+//! can recompute every result and every link of the chain with `/fair/verify`.
+//!
+//! Ledgers live in memory until deleted or until they go [`LEDGER_TTL`]
+//! without being used, so a log should be saved before it expires if it needs
+//! to be verified later.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{
+        rejection::{BytesRejection, JsonRejection},
+        FromRequest, Path, Request, State,
+    },
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use dashmap::DashMap;
+use data_encoding::HEXLOWER;
+use rand::{rngs::OsRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use server_core::shutdown::Shutdown;
+use sha2::{Digest, Sha256};
+
+use crate::error::ApiError;
+use crate::generator::{ParamError, Sampler};
+use crate::lists::{permutation, sample_indices, Picker};
+
+pub const MAX_LEDGERS: usize = 10_000;
+pub const MAX_ENTRIES: usize = 100_000;
+pub const LEDGER_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// What to draw. Stored verbatim in the log so the draw can be recomputed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum DrawSpec {
+    Int {
+        start: i64,
+        end: i64,
+        #[serde(default)]
+        inclusive: bool,
+    },
+    Float {
+        start: f64,
+        end: f64,
+        #[serde(default)]
+        inclusive: bool,
+    },
+    Pick {
+        items: Vec<JsonValue>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        weights: Option<Vec<f64>>,
+    },
+    Sample {
+        items: Vec<JsonValue>,
+        k: usize,
+    },
+    Shuffle {
+        items: Vec<JsonValue>,
+    },
+}
+
+impl DrawSpec {
+    /// Run the draw. Results only depend on the spec and `rng`.
+    pub fn run(&self, rng: &mut ChaCha20Rng) -> Result<JsonValue, ParamError> {
+        let pick = |items: &[JsonValue], indices: Vec<usize>| {
+            JsonValue::Array(indices.into_iter().map(|i| items[i].clone()).collect())
+        };
+        Ok(match self {
+            DrawSpec::Int {
+                start,
+                end,
+                inclusive,
+            } => json!(Sampler::int(*start, *end, *inclusive)?.sample(rng)),
+            DrawSpec::Float {
+                start,
+                end,
+                inclusive,
+            } => json!(Sampler::float(*start, *end, *inclusive)?.sample(rng)),
+            DrawSpec::Pick { items, weights } => {
+                let picker = Picker::new(items.len(), weights.as_deref())?;
+                items[picker.pick(rng)].clone()
+            }
+            DrawSpec::Sample { items, k } => {
+                if *k > items.len() {
+                    return Err(ParamError::new(
+                        "k",
+                        format!("must not exceed the number of items ({})", items.len()),
+                    ));
+                }
+                pick(items, sample_indices(items.len(), *k, rng))
+            }
+            DrawSpec::Shuffle { items } => pick(items, permutation(items.len(), rng)),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub index: u64,
+    pub spec: DrawSpec,
+    pub result: JsonValue,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+/// The random source for draw `index`: ChaCha20 keyed by
+/// `SHA-256(server_seed || client_seed)`, one stream per entry.
+fn draw_rng(server_seed: &[u8], client_seed: &str, index: u64) -> ChaCha20Rng {
+    let key: [u8; 32] = Sha256::new()
+        .chain_update(server_seed)
+        .chain_update(client_seed.as_bytes())
+        .finalize()
+        .into();
+    let mut rng = ChaCha20Rng::from_seed(key);
+    rng.set_stream(index);
+    rng
+}
+
+/// Hash of one link of the chain over the previous hash and the canonical
+/// (key-sorted) JSON of the entry's index, spec and result. Verification
+/// re-serializes values parsed from the published log, which only gives the
+/// same text because serde_json's `float_roundtrip` parses floats exactly.
+fn entry_hash(prev_hash: &str, index: u64, spec: &DrawSpec, result: &JsonValue) -> String {
+    let body = json!({ "index": index, "spec": spec, "result": result });
+    let digest = Sha256::new()
+        .chain_update(prev_hash.as_bytes())
+        .chain_update(b"\n")
+        .chain_update(body.to_string().as_bytes())
+        .finalize();
+    HEXLOWER.encode(&digest)
+}
+
+fn commitment(server_seed: &[u8]) -> String {
+    HEXLOWER.encode(&Sha256::digest(server_seed))
+}
+
+struct Ledger {
+    server_seed: [u8; 32],
+    client_seed: String,
+    commitment: String,
+    revealed: bool,
+    entries: Vec<Entry>,
+    expires_at: Instant,
+}
+
+impl Ledger {
+    fn head(&self) -> &str {
+        self.entries
+            .last()
+            .map_or(self.commitment.as_str(), |e| e.hash.as_str())
+    }
+
+    fn view(&self, id: &str) -> LedgerView {
+        LedgerView {
+            id: id.to_string(),
+            commitment: self.commitment.clone(),
+            client_seed: self.client_seed.clone(),
+            server_seed: self.revealed.then(|| HEXLOWER.encode(&self.server_seed)),
+            head: self.head().to_string(),
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+/// In-memory store of fairness ledgers. Ledgers expire after `ttl` without
+/// being used.
+#[derive(Clone)]
+pub struct FairStore {
+    ledgers: Arc<DashMap<String, Ledger>>,
+    ttl: Duration,
+    capacity: usize,
+}
+
+impl Default for FairStore {
+    fn default() -> Self {
+        FairStore::new(LEDGER_TTL, MAX_LEDGERS)
+    }
+}
+
+impl FairStore {
+    /// A store holding at most `capacity` ledgers, each kept for `ttl` after
+    /// its last use.
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        FairStore {
+            ledgers: Arc::default(),
+            ttl,
+            capacity,
+        }
+    }
+
+    /// Drop every expired ledger; returns how many were removed.
+    pub fn purge_expired(&self) -> usize {
+        let now = Instant::now();
+        let before = self.ledgers.len();
+        self.ledgers.retain(|_, ledger| ledger.expires_at > now);
+        before - self.ledgers.len()
+    }
+
+    /// Periodically purge expired ledgers in the background until shutdown.
+    pub fn spawn_sweeper(
+        &self,
+        every: Duration,
+        shutdown: &Shutdown,
+    ) -> tokio::task::JoinHandle<()> {
+        let store = self.clone();
+        let token = shutdown.token();
+        shutdown.spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    () = token.cancelled() => break,
+                }
+                store.purge_expired();
+            }
+        })
+    }
+
+    fn with_ledger<T>(
+        &self,
+        id: &str,
+        f: impl FnOnce(&mut Ledger) -> Result<T, ApiError>,
+    ) -> Result<T, ApiError> {
+        let not_found = || ApiError::not_found(format!("no fairness ledger `{id}`"));
+        let mut ledger = self.ledgers.get_mut(id).ok_or_else(not_found)?;
+        let now = Instant::now();
+        if ledger.expires_at <= now {
+            drop(ledger);
+            self.ledgers.remove(id);
+            return Err(not_found());
+        }
+        ledger.expires_at = now + self.ttl;
+        f(&mut ledger)
+    }
+}
+
+/// A ledger as published. `server_seed` is only present once revealed; the
+/// whole object can be posted back to `/fair/verify`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LedgerView {
+    #[serde(default)]
+    pub id: String,
+    pub commitment: String,
+    #[serde(default)]
+    pub client_seed: String,
+    pub server_seed: Option<String>,
+    #[serde(default)]
+    pub head: String,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Default, Deserialize)]
+pub struct CreateLedger {
+    #[serde(default)]
+    client_seed: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyIssue {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<u64>,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Verification {
+    pub valid: bool,
+    pub entries_checked: usize,
+    pub issues: Vec<VerifyIssue>,
+}
+
+/// Recompute every result and hash of a revealed ledger.
+pub fn verify(log: &LedgerView) -> Verification {
+    let mut issues = Vec::new();
+    let mut issue =
+        |index: Option<u64>, message: String| issues.push(VerifyIssue { index, message });
+
+    let server_seed = match log
+        .server_seed
+        .as_deref()
+        .map(|s| HEXLOWER.decode(s.as_bytes()))
+    {
+        Some(Ok(seed)) => seed,
+        Some(Err(_)) => {
+            issue(None, "server_seed is not lowercase hex".to_string());
+            Vec::new()
+        }
+        None => {
+            issue(None, "server_seed has not been revealed".to_string());
+            Vec::new()
+        }
+    };
+    if log.server_seed.is_some() && commitment(&server_seed) != log.commitment {
+        issue(
+            None,
+            "server_seed does not match the commitment".to_string(),
+        );
+    }
+
+    let mut prev_hash = log.commitment.clone();
+    for (position, entry) in log.entries.iter().enumerate() {
+        let index = Some(entry.index);
+        if entry.index != position as u64 {
+            issue(index, format!("expected index {position}"));
+        }
+        if entry.prev_hash != prev_hash {
+            issue(
+                index,
+                "prev_hash does not link to the previous entry".to_string(),
+            );
+        }
+        if entry_hash(&entry.prev_hash, entry.index, &entry.spec, &entry.result) != entry.hash {
+            issue(index, "hash does not match the entry".to_string());
+        }
+        if log.server_seed.is_some() {
+            let mut rng = draw_rng(&server_seed, &log.client_seed, entry.index);
+            match entry.spec.run(&mut rng) {
+                Ok(result) if result == entry.result => {}
+                Ok(result) => issue(index, format!("result should be {result}")),
+                Err(err) => issue(index, format!("invalid spec: {err}")),
+            }
+        }
+        prev_hash = entry.hash.clone();
+    }
+    if !log.head.is_empty() && log.head != prev_hash {
+        issue(None, "head does not match the last entry".to_string());
+    }
+
+    Verification {
+        valid: issues.is_empty(),
+        entries_checked: log.entries.len(),
+        issues,
+    }
+}
+
+/// `POST /fair`, optionally with `{"client_seed": "..."}`: create a ledger and
+/// publish the commitment to its seed.
+pub async fn create_handler(
+    State(store): State<FairStore>,
+    headers: HeaderMap,
+    body: Result<Bytes, BytesRejection>,
+) -> Result<(StatusCode, Json<LedgerView>), ApiError> {
+    let body = body.map_err(JsonRejection::from)?;
+    let request = if body.is_empty() {
+        CreateLedger::default()
+    } else {
+        // Any body at all goes through the usual `Json` checks.
+        let mut request = Request::new(Body::from(body));
+        *request.headers_mut() = headers;
+        let Json(request) = Json::<CreateLedger>::from_request(request, &()).await?;
+        request
+    };
+    store.purge_expired();
+    if store.ledgers.len() >= store.capacity {
+        return Err(ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "too_many_ledgers",
+            "the ledger store is full, try again later",
+        ));
+    }
+    let mut server_seed = [0u8; 32];
+    OsRng.fill_bytes(&mut server_seed);
+    let mut id = [0u8; 16];
+    OsRng.fill_bytes(&mut id);
+    let id = HEXLOWER.encode(&id);
+    let ledger = Ledger {
+        server_seed,
+        client_seed: request.client_seed,
+        commitment: commitment(&server_seed),
+        revealed: false,
+        entries: Vec::new(),
+        expires_at: Instant::now() + store.ttl,
+    };
+    let view = ledger.view(&id);
+    store.ledgers.insert(id, ledger);
+    Ok((StatusCode::CREATED, Json(view)))
+}
+
+/// `GET /fair/:id`: the commitment and the full log.
+pub async fn log_handler(
+    State(store): State<FairStore>,
+    Path(id): Path<String>,
+) -> Result<Json<LedgerView>, ApiError> {
+    store.with_ledger(&id, |ledger| Ok(Json(ledger.view(&id))))
+}
+
+/// `POST /fair/:id/draw` with a [`DrawSpec`]: draw and append to the chain.
+pub async fn draw_handler(
+    State(store): State<FairStore>,
+    Path(id): Path<String>,
+    spec: Result<Json<DrawSpec>, JsonRejection>,
+) -> Result<(StatusCode, Json<Entry>), ApiError> {
+    let Json(spec) = spec?;
+    store.with_ledger(&id, |ledger| {
+        if ledger.revealed {
+            return Err(ApiError::conflict(
+                "revealed",
+                "the seed has been revealed, no more draws are accepted",
+            ));
+        }
+        if ledger.entries.len() >= MAX_ENTRIES {
+            return Err(ApiError::conflict("full", "the ledger is full"));
+        }
+        let index = ledger.entries.len() as u64;
+        let mut rng = draw_rng(&ledger.server_seed, &ledger.client_seed, index);
+        let result = spec.run(&mut rng)?;
+        let prev_hash = ledger.head().to_string();
+        let hash = entry_hash(&prev_hash, index, &spec, &result);
+        let entry = Entry {
+            index,
+            spec,
+            result,
+            prev_hash,
+            hash,
+        };
+        ledger.entries.push(entry.clone());
+        Ok((StatusCode::CREATED, Json(entry)))
+    })
+}
+
+/// `POST /fair/:id/reveal`: close the ledger and publish the server seed.
+pub async fn reveal_handler(
+    State(store): State<FairStore>,
+    Path(id): Path<String>,
+) -> Result<Json<LedgerView>, ApiError> {
+    store.with_ledger(&id, |ledger| {
+        ledger.revealed = true;
+        Ok(Json(ledger.view(&id)))
+    })
+}
+
+/// `DELETE /fair/:id`: discard a ledger and free its slot.
+pub async fn delete_handler(
+    State(store): State<FairStore>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    match store.ledgers.remove(&id) {
+        Some(_) => Ok(StatusCode::NO_CONTENT),
+        None => Err(ApiError::not_found(format!("no fairness ledger `{id}`"))),
+    }
+}
+
+/// `POST /fair/verify` with a revealed ledger as returned by `GET /fair/:id`.
+pub async fn verify_handler(
+    log: Result<Json<LedgerView>, JsonRejection>,
+) -> Result<Json<Verification>, ApiError> {
+    let Json(log) = log?;
+    Ok(Json(verify(&log)))
+}
@@ -0,0 +1,162 @@
+use std::fmt;
+
+use rand::Rng;
+use serde::Serialize;
+
+use crate::distribution::{DistParameters, Distribution};
+
+/// A query parameter that failed validation.
+#[derive(Debug)]
+pub struct ParamError {
+    pub parameter: &'static str,
+    pub message: String,
+}
+
+impl ParamError {
+    pub fn new(parameter: &'static str, message: impl Into<String>) -> Self {
+        ParamError {
+            parameter,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.parameter, self.message)
+    }
+}
+
+/// A single generated value.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(v) => write!(f, "{v}"),
+            Value::Float(v) => write!(f, "{v}"),
+            Value::Bool(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+/// Header line of a CSV batch.
+pub const CSV_HEADER: &str = "index,value\n";
+
+/// One NDJSON line of a batch. The HTTP batch body and the command line use
+/// the same record formats.
+pub fn ndjson_record(index: u64, value: Value) -> String {
+    format!(
+        "{}\n",
+        serde_json::json!({ "index": index, "value": value })
+    )
+}
+
+/// One CSV line of a batch.
+pub fn csv_record(index: u64, value: Value) -> String {
+    format!("{index},{value}\n")
+}
+
+/// A validated description of what to generate.
+///
+/// Constructors check the parameters up front so that `sample` can never panic,
+/// unlike a bare `gen_range(start..end)` with `start >= end`.
+#[derive(Debug, Clone)]
+pub enum Sampler {
+    Int {
+        start: i64,
+        end: i64,
+        inclusive: bool,
+    },
+    Float {
+        start: f64,
+        end: f64,
+        inclusive: bool,
+    },
+    Bool {
+        p: f64,
+    },
+    Dist(Distribution),
+}
+
+impl Sampler {
+    pub fn int(start: i64, end: i64, inclusive: bool) -> Result<Self, ParamError> {
+        check_bounds(start < end, start == end, inclusive)?;
+        Ok(Sampler::Int {
+            start,
+            end,
+            inclusive,
+        })
+    }
+
+    pub fn float(start: f64, end: f64, inclusive: bool) -> Result<Self, ParamError> {
+        if !start.is_finite() {
+            return Err(ParamError::new("start", "must be a finite number"));
+        }
+        if !end.is_finite() {
+            return Err(ParamError::new("end", "must be a finite number"));
+        }
+        check_bounds(start < end, start == end, inclusive)?;
+        // `gen_range` panics when the width of the range overflows.
+        if !(end - start).is_finite() {
+            return Err(ParamError::new(
+                "end",
+                "range from start is too wide to sample",
+            ));
+        }
+        Ok(Sampler::Float {
+            start,
+            end,
+            inclusive,
+        })
+    }
+
+    pub fn bool(p: f64) -> Result<Self, ParamError> {
+        if !(0.0..=1.0).contains(&p) {
+            return Err(ParamError::new("p", "must be between 0 and 1"));
+        }
+        Ok(Sampler::Bool { p })
+    }
+
+    pub fn dist(params: &DistParameters) -> Result<Self, ParamError> {
+        Distribution::new(params).map(Sampler::Dist)
+    }
+
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Value {
+        match *self {
+            Sampler::Int {
+                start,
+                end,
+                inclusive: true,
+            } => Value::Int(rng.gen_range(start..=end)),
+            Sampler::Int { start, end, .. } => Value::Int(rng.gen_range(start..end)),
+            Sampler::Float {
+                start,
+                end,
+                inclusive: true,
+            } => Value::Float(rng.gen_range(start..=end)),
+            Sampler::Float { start, end, .. } => Value::Float(rng.gen_range(start..end)),
+            Sampler::Bool { p } => Value::Bool(rng.gen_bool(p)),
+            Sampler::Dist(ref dist) => dist.sample(rng),
+        }
+    }
+}
+
+fn check_bounds(less: bool, equal: bool, inclusive: bool) -> Result<(), ParamError> {
+    if less || (equal && inclusive) {
+        Ok(())
+    } else if equal {
+        Err(ParamError::new(
+            "end",
+            "must be greater than start for an exclusive range",
+        ))
+    } else {
+        Err(ParamError::new("end", "must not be less than start"))
+    }
+}
@@ -0,0 +1,100 @@
+//! Random number service: the HTTP router and the generators behind it,
+//! shared with the command-line interface in `main.rs`.
+
+pub mod cli;
+pub mod dice;
+pub mod distribution;
+pub mod error;
+pub mod fairness;
+pub mod generator;
+pub mod lists;
+pub mod negotiate;
+pub mod random;
+pub mod ratelimit;
+pub mod seed;
+pub mod selftest;
+pub mod session;
+pub mod state;
+pub mod static_files;
+pub mod stats;
+pub mod token;
+
+use axum::{
+    middleware,
+    routing::{get, post},
+    Router,
+};
+use rand::RngCore;
+use server_core::health::Readiness;
+
+use distribution::DistParameters;
+use random::{events_handler, random_handler, BoolParameters, FloatParameters, IntParameters};
+use selftest::selftest_handler;
+use state::AppState;
+
+/// Every route of the service, rate limited by `state.limiter`.
+pub fn app(state: AppState) -> Router {
+    Router::new()
+        .route("/", get(static_files::page_handler))
+        .route("/sina.html", get(static_files::page_handler))
+        .route("/sina_files/*path", get(static_files::asset_handler))
+        .route("/random/int", get(random_handler::<IntParameters>))
+        .route("/random/int/events", get(events_handler::<IntParameters>))
+        .route("/random/float", get(random_handler::<FloatParameters>))
+        .route(
+            "/random/float/events",
+            get(events_handler::<FloatParameters>),
+        )
+        .route("/random/bool", get(random_handler::<BoolParameters>))
+        .route("/random/bool/events", get(events_handler::<BoolParameters>))
+        .route("/random/dist", get(random_handler::<DistParameters>))
+        .route("/random/dist/events", get(events_handler::<DistParameters>))
+        .route("/dice", get(dice::dice_handler))
+        .route("/dice/:expr", get(dice::dice_path_handler))
+        .route("/shuffle", post(lists::shuffle_handler))
+        .route("/sample", post(lists::sample_handler))
+        .route("/pick", post(lists::pick_handler))
+        .route("/token", get(token::token_handler))
+        .route("/sessions", post(session::create_handler))
+        .route(
+            "/sessions/:name",
+            get(session::inspect_handler).delete(session::delete_handler),
+        )
+        .route("/sessions/:name/draw", post(session::draw_handler))
+        .route("/sessions/:name/reset", post(session::reset_handler))
+        .route("/fair", post(fairness::create_handler))
+        .route("/fair/verify", post(fairness::verify_handler))
+        .route(
+            "/fair/:id",
+            get(fairness::log_handler).delete(fairness::delete_handler),
+        )
+        .route("/fair/:id/draw", post(fairness::draw_handler))
+        .route("/fair/:id/reveal", post(fairness::reveal_handler))
+        .route("/selftest/int", get(selftest_handler::<IntParameters>))
+        .route("/selftest/float", get(selftest_handler::<FloatParameters>))
+        .route("/selftest/bool", get(selftest_handler::<BoolParameters>))
+        .route("/selftest/dist", get(selftest_handler::<DistParameters>))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            ratelimit::rate_limit,
+        ))
+        .with_state(state)
+}
+
+/// Readiness checks for `/readyz`.
+pub fn readiness(state: &AppState) -> Readiness {
+    Readiness::new()
+        .with_check("static_files", {
+            let files = state.files.clone();
+            move || {
+                let files = files.clone();
+                async move { files.check().await }
+            }
+        })
+        .with_check("os_rng", || async {
+            let mut byte = [0u8; 1];
+            rand::rngs::OsRng
+                .try_fill_bytes(&mut byte)
+                .map_err(|err| err.to_string())
+        })
+}
@@ -0,0 +1,195 @@
+use axum::{extract::rejection::JsonRejection, Json};
+use rand::{distributions::Distribution, Rng};
+use rand_distr::WeightedIndex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::distribution::check_weights;
+use crate::error::ApiError;
+use crate::generator::ParamError;
+use crate::seed::{SeedParameters, SequenceInfo, Source};
+
+/// Fisher–Yates shuffle of the indices `0..len`.
+pub fn permutation<R: Rng + ?Sized>(len: usize, rng: &mut R) -> Vec<usize> {
+    sample_indices(len, len, rng)
+}
+
+/// Pick `k` distinct indices out of `0..len` with a partial Fisher–Yates shuffle,
+/// in the order they were drawn.
+pub fn sample_indices<R: Rng + ?Sized>(len: usize, k: usize, rng: &mut R) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..len).collect();
+    for i in 0..k.min(len.saturating_sub(1)) {
+        let j = rng.gen_range(i..len);
+        indices.swap(i, j);
+    }
+    indices.truncate(k);
+    indices
+}
+
+/// Draws indices into a list, uniformly or by weight.
+#[derive(Debug, Clone)]
+pub enum Picker {
+    Uniform(usize),
+    Weighted(WeightedIndex<f64>),
+}
+
+impl Picker {
+    pub fn new(len: usize, weights: Option<&[f64]>) -> Result<Self, ParamError> {
+        if len == 0 {
+            return Err(ParamError::new("items", "must not be empty"));
+        }
+        match weights {
+            None => Ok(Picker::Uniform(len)),
+            Some(weights) if weights.len() != len => Err(ParamError::new(
+                "weights",
+                format!("must have one weight per item ({len})"),
+            )),
+            Some(weights) => {
+                check_weights(weights)?;
+                WeightedIndex::new(weights)
+                    .map(Picker::Weighted)
+                    .map_err(|e| ParamError::new("weights", e.to_string()))
+            }
+        }
+    }
+
+    pub fn pick<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        match self {
+            Picker::Uniform(len) => rng.gen_range(0..*len),
+            Picker::Weighted(index) => index.sample(rng),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ShuffleRequest {
+    items: Vec<JsonValue>,
+    #[serde(flatten)]
+    seed: SeedParameters,
+}
+
+#[derive(Deserialize)]
+pub struct SampleRequest {
+    items: Vec<JsonValue>,
+    k: usize,
+    #[serde(flatten)]
+    seed: SeedParameters,
+}
+
+#[derive(Deserialize)]
+pub struct PickRequest {
+    items: Vec<JsonValue>,
+    weights: Option<Vec<f64>>,
+    #[serde(default = "default_pick_count")]
+    count: usize,
+    #[serde(flatten)]
+    seed: SeedParameters,
+}
+
+fn default_pick_count() -> usize {
+    1
+}
+
+/// Maximum number of picks (with replacement) in one request.
+pub const MAX_PICKS: usize = 10_000;
+
+#[derive(Serialize)]
+pub struct ListResponse {
+    pub items: Vec<JsonValue>,
+    /// Positions of the returned items in the submitted list.
+    pub indices: Vec<usize>,
+    #[serde(flatten)]
+    pub sequence: SequenceInfo,
+}
+
+impl ListResponse {
+    fn new(items: &[JsonValue], indices: Vec<usize>, sequence: SequenceInfo) -> Self {
+        ListResponse {
+            items: indices.iter().map(|&i| items[i].clone()).collect(),
+            indices,
+            sequence,
+        }
+    }
+}
+
+/// A random permutation of `items`.
+pub fn shuffle(items: &[JsonValue], seed: &SeedParameters) -> Result<ListResponse, ParamError> {
+    let len = items.len();
+    let mut source =
+        Source::from_parameters_with_cost(seed, len as u64, |rng| permutation(len, rng))?;
+    let sequence = source.info();
+    let indices = source.next_with(|rng| permutation(len, rng));
+    Ok(ListResponse::new(items, indices, sequence))
+}
+
+/// `k` of `items` without replacement.
+pub fn sample(
+    items: &[JsonValue],
+    k: usize,
+    seed: &SeedParameters,
+) -> Result<ListResponse, ParamError> {
+    let len = items.len();
+    if k > len {
+        return Err(ParamError::new(
+            "k",
+            format!("must not exceed the number of items ({len})"),
+        ));
+    }
+    // Each sample still builds the full index list.
+    let mut source =
+        Source::from_parameters_with_cost(seed, len as u64, |rng| sample_indices(len, k, rng))?;
+    let sequence = source.info();
+    let indices = source.next_with(|rng| sample_indices(len, k, rng));
+    Ok(ListResponse::new(items, indices, sequence))
+}
+
+/// `count` of `items` with replacement, weighted when `weights` is given.
+pub fn pick(
+    items: &[JsonValue],
+    weights: Option<&[f64]>,
+    count: usize,
+    seed: &SeedParameters,
+) -> Result<ListResponse, ParamError> {
+    if !(1..=MAX_PICKS).contains(&count) {
+        return Err(ParamError::new(
+            "count",
+            format!("must be between 1 and {MAX_PICKS}"),
+        ));
+    }
+    let picker = Picker::new(items.len(), weights)?;
+    let pick = |rng: &mut _| (0..count).map(|_| picker.pick(rng)).collect::<Vec<_>>();
+    let mut source = Source::from_parameters_with_cost(seed, count as u64, pick)?;
+    let sequence = source.info();
+    let indices = source.next_with(pick);
+    Ok(ListResponse::new(items, indices, sequence))
+}
+
+/// `POST /shuffle` with `{"items": [...]}`: a random permutation of the items.
+pub async fn shuffle_handler(
+    request: Result<Json<ShuffleRequest>, JsonRejection>,
+) -> Result<Json<ListResponse>, ApiError> {
+    let Json(request) = request?;
+    Ok(Json(shuffle(&request.items, &request.seed)?))
+}
+
+/// `POST /sample` with `{"items": [...], "k": 3}`: `k` items without replacement.
+pub async fn sample_handler(
+    request: Result<Json<SampleRequest>, JsonRejection>,
+) -> Result<Json<ListResponse>, ApiError> {
+    let Json(request) = request?;
+    Ok(Json(sample(&request.items, request.k, &request.seed)?))
+}
+
+/// `POST /pick` with `{"items": [...], "weights": [...]}`: `count` picks with
+/// replacement, weighted when `weights` is given.
+pub async fn pick_handler(
+    request: Result<Json<PickRequest>, JsonRejection>,
+) -> Result<Json<ListResponse>, ApiError> {
+    let Json(request) = request?;
+    Ok(Json(pick(
+        &request.items,
+        request.weights.as_deref(),
+        request.count,
+        &request.seed,
+    )?))
+}
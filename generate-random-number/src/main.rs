@@ -1,24 +1,48 @@
-use axum::{extract::Query,response::Html,routing::get,Router};
-use rand::{thread_rng,Rng};
-use serde::Deserialize;
+use std::{process::ExitCode, time::Duration};
+
+use clap::Parser;
+use generate_random_number::{
+    cli::{self, Cli, Command, ServeArgs},
+    ratelimit::RateLimiter,
+    state::AppState,
+    static_files::StaticFiles,
+};
+use server_core::Server;
 
-async fn handler(Query(range):Query<RangeParameters>) -> Html<String>{
-    let random_number = thread_rng().gen_range(range.start..range.end);
-    Html(format!("<h1>Random Number:{}</h1>",random_number))
-}
-async fn handler_html() -> Html<&'static str>{
-    Html(include_str!("../sina.html"))
-}
 #[tokio::main]
-async fn main() {
-    let app = Router::new().route("/", get(handler_html));
-    let listener
-        = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    println!("listening on {:?}", listener);
-    axum::serve(listener,app).await.unwrap();
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match cli.command.unwrap_or(Command::Serve(cli.serve)) {
+        Command::Serve(args) => serve(args).await,
+        Command::Offline(command) => cli::run(command),
+    }
 }
-#[derive(Deserialize)]
-struct RangeParameters{
-    start:usize,
-    end:usize,
+
+async fn serve(args: ServeArgs) -> ExitCode {
+    let server = match Server::from_args(&args.server) {
+        Ok(server) => server,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::from(2);
+        }
+    };
+    let limiter = match RateLimiter::new(args.rate_limit_config()) {
+        Ok(limiter) => limiter,
+        Err(err) => {
+            eprintln!("error: failed to load quota snapshot: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let files = args
+        .static_root
+        .map_or_else(StaticFiles::embedded, StaticFiles::new);
+    let state = AppState::new(files, limiter);
+    state.sessions.spawn_sweeper(Duration::from_secs(30), server.shutdown());
+    state.fair.spawn_sweeper(Duration::from_secs(60), server.shutdown());
+    state.limiter.spawn_sweeper(Duration::from_secs(60), server.shutdown());
+    let readiness = generate_random_number::readiness(&state);
+    server
+        .readiness(readiness)
+        .serve(generate_random_number::app(state))
+        .await
 }
@@ -0,0 +1,98 @@
+//! `Accept` header negotiation for the `/random/*` routes.
+
+use axum::http::{header, HeaderMap, StatusCode};
+
+use crate::error::ApiError;
+use crate::random::Format;
+
+impl Format {
+    pub fn media_type(self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Html => "text/html",
+            Format::Text => "text/plain",
+            Format::Cbor => "application/cbor",
+            Format::Ndjson => "application/x-ndjson",
+            Format::Csv => "text/csv",
+        }
+    }
+}
+
+/// One `type/subtype;q=...` entry of an `Accept` header.
+struct MediaRange<'a> {
+    kind: &'a str,
+    subtype: &'a str,
+    q: f32,
+}
+
+impl MediaRange<'_> {
+    /// How specifically this range names `media_type`: 2 for an exact match,
+    /// 1 for `type/*`, 0 for `*/*`, or `None` if it does not match at all.
+    fn specificity(&self, media_type: &str) -> Option<u8> {
+        let (kind, subtype) = media_type.split_once('/')?;
+        match (self.kind, self.subtype) {
+            ("*", "*") => Some(0),
+            (k, "*") if k.eq_ignore_ascii_case(kind) => Some(1),
+            (k, s) if k.eq_ignore_ascii_case(kind) && s.eq_ignore_ascii_case(subtype) => Some(2),
+            _ => None,
+        }
+    }
+}
+
+fn parse_accept(value: &str) -> Vec<MediaRange<'_>> {
+    value
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';').map(str::trim);
+            let (kind, subtype) = parts.next()?.split_once('/')?;
+            let q = parts
+                .filter_map(|p| p.split_once('='))
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+                .map_or(Some(1.0), |(_, q)| q.trim().parse::<f32>().ok())?;
+            Some(MediaRange {
+                kind: kind.trim(),
+                subtype: subtype.trim(),
+                q: q.clamp(0.0, 1.0),
+            })
+        })
+        .collect()
+}
+
+/// Pick the format for a response out of `offered`, which is listed in the
+/// server's order of preference.
+///
+/// Each offered format takes the quality of the most specific range that
+/// matches it; ties go to the earlier offer. Without an `Accept` header the
+/// first offer is used, and if nothing acceptable is offered the result is
+/// `406 Not Acceptable`.
+pub fn negotiate(headers: &HeaderMap, offered: &[Format]) -> Result<Format, ApiError> {
+    let accept: Vec<_> = headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(parse_accept)
+        .collect();
+    if accept.is_empty() {
+        return Ok(offered[0]);
+    }
+
+    let mut best: Option<(Format, f32)> = None;
+    for &format in offered {
+        let q = accept
+            .iter()
+            .filter_map(|range| Some((range.specificity(format.media_type())?, range.q)))
+            .max_by_key(|&(specificity, _)| specificity)
+            .map_or(0.0, |(_, q)| q);
+        if q > 0.0 && best.is_none_or(|(_, best_q)| q > best_q) {
+            best = Some((format, q));
+        }
+    }
+    best.map(|(format, _)| format).ok_or_else(|| {
+        let offered: Vec<_> = offered.iter().map(|f| f.media_type()).collect();
+        ApiError::new(
+            StatusCode::NOT_ACCEPTABLE,
+            "not_acceptable",
+            format!("this resource is available as {}", offered.join(", ")),
+        )
+    })
+}
@@ -0,0 +1,301 @@
+use std::{convert::Infallible, time::Duration};
+
+use askama::Template;
+use axum::{
+    body::Body,
+    extract::{rejection::QueryRejection, Query},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Response,
+    },
+    Json,
+};
+use futures::stream::{self, Stream};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::distribution::DistParameters;
+use crate::error::ApiError;
+use crate::generator::{csv_record, ndjson_record, ParamError, Sampler, Value, CSV_HEADER};
+use crate::negotiate::negotiate;
+use crate::seed::{SeedParameters, SequenceInfo, Source};
+
+/// Largest `count` accepted by the batch endpoints.
+pub const MAX_COUNT: u64 = 10_000_000;
+/// Number of values rendered into each chunk of a streamed batch body.
+const CHUNK_SIZE: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Json,
+    Html,
+    Text,
+    Cbor,
+    Ndjson,
+    Csv,
+}
+
+/// Formats for a single value, in the order preferred when `Accept` allows several.
+const SINGLE_FORMATS: &[Format] = &[Format::Json, Format::Html, Format::Text, Format::Cbor];
+/// Formats for a batch requested with `count`.
+const BATCH_FORMATS: &[Format] = &[Format::Ndjson, Format::Csv];
+
+/// Query parameters that describe a [`Sampler`] for one of the `/random/*` routes.
+pub trait SamplerParameters: DeserializeOwned + Send + 'static {
+    fn sampler(self) -> Result<Sampler, ParamError>;
+}
+
+#[derive(Deserialize)]
+pub struct RangeParameters<T> {
+    start: T,
+    end: T,
+    #[serde(default)]
+    inclusive: bool,
+}
+
+pub type IntParameters = RangeParameters<i64>;
+pub type FloatParameters = RangeParameters<f64>;
+
+impl SamplerParameters for IntParameters {
+    fn sampler(self) -> Result<Sampler, ParamError> {
+        Sampler::int(self.start, self.end, self.inclusive)
+    }
+}
+
+impl SamplerParameters for FloatParameters {
+    fn sampler(self) -> Result<Sampler, ParamError> {
+        Sampler::float(self.start, self.end, self.inclusive)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BoolParameters {
+    #[serde(default = "default_p")]
+    p: f64,
+}
+
+fn default_p() -> f64 {
+    0.5
+}
+
+impl SamplerParameters for BoolParameters {
+    fn sampler(self) -> Result<Sampler, ParamError> {
+        Sampler::bool(self.p)
+    }
+}
+
+impl SamplerParameters for DistParameters {
+    fn sampler(self) -> Result<Sampler, ParamError> {
+        Sampler::dist(&self)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct OutputParameters {
+    format: Option<Format>,
+    count: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct EventParameters {
+    #[serde(default = "default_interval_ms")]
+    interval_ms: u64,
+    count: Option<u64>,
+}
+
+fn default_interval_ms() -> u64 {
+    1000
+}
+
+type SeedQuery = Result<Query<SeedParameters>, QueryRejection>;
+
+#[derive(Serialize)]
+struct RandomResponse {
+    value: Value,
+    #[serde(flatten)]
+    sequence: SequenceInfo,
+}
+
+#[derive(Template)]
+#[template(path = "random.html")]
+struct RandomTemplate {
+    value: Value,
+    seed: u64,
+    stream: u64,
+    index: u64,
+}
+
+/// `GET /random/{int,float,bool,dist}`: one value as JSON, HTML, plain text or
+/// CBOR, or a streamed NDJSON/CSV batch when `count` is given.
+///
+/// The `format` parameter wins over the `Accept` header.
+pub async fn random_handler<P: SamplerParameters>(
+    headers: HeaderMap,
+    params: Result<Query<P>, QueryRejection>,
+    output: Result<Query<OutputParameters>, QueryRejection>,
+    seed: SeedQuery,
+) -> Result<Response, ApiError> {
+    let Query(params) = params?;
+    let Query(output) = output?;
+    let Query(seed) = seed?;
+    let sampler = params.sampler()?;
+    let mut source = Source::from_parameters(&seed, |rng| sampler.sample(rng))?;
+
+    let format = match (output.format, output.count) {
+        (Some(format), _) => format,
+        (None, Some(_)) => negotiate(&headers, BATCH_FORMATS)?,
+        (None, None) => negotiate(&headers, SINGLE_FORMATS)?,
+    };
+    let mut response = match format {
+        Format::Json | Format::Html | Format::Text | Format::Cbor if output.count.is_some() => {
+            return Err(ParamError::new(
+                "format",
+                "batches with count are only available as ndjson or csv",
+            )
+            .into())
+        }
+        Format::Json | Format::Html | Format::Text | Format::Cbor => {
+            let sequence = source.info();
+            let value = source.next_with(|rng| sampler.sample(rng));
+            single_response(value, sequence, format)?
+        }
+        Format::Ndjson | Format::Csv => {
+            let count = output.count.unwrap_or(1);
+            if count > MAX_COUNT {
+                return Err(
+                    ParamError::new("count", format!("must not exceed {MAX_COUNT}")).into(),
+                );
+            }
+            batch_response(sampler, source, count, format)
+        }
+    };
+    response
+        .headers_mut()
+        .insert(header::VARY, HeaderValue::from_static("accept"));
+    Ok(response)
+}
+
+/// `GET /random/{int,float,bool,dist}/events`: a Server-Sent Events stream emitting
+/// a new value every `interval_ms`, optionally stopping after `count` events.
+pub async fn events_handler<P: SamplerParameters>(
+    params: Result<Query<P>, QueryRejection>,
+    events: Result<Query<EventParameters>, QueryRejection>,
+    seed: SeedQuery,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let Query(params) = params?;
+    let Query(events) = events?;
+    let Query(seed) = seed?;
+    if !(10..=3_600_000).contains(&events.interval_ms) {
+        return Err(ParamError::new("interval_ms", "must be between 10 and 3600000").into());
+    }
+    let sampler = params.sampler()?;
+    let source = Source::from_parameters(&seed, |rng| sampler.sample(rng))?;
+
+    let mut interval = tokio::time::interval(Duration::from_millis(events.interval_ms));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let remaining = events.count.unwrap_or(u64::MAX);
+    let stream = stream::unfold(
+        (source, interval, remaining),
+        move |(mut source, mut interval, remaining)| {
+            let sampler = sampler.clone();
+            async move {
+                if remaining == 0 {
+                    return None;
+                }
+                interval.tick().await;
+                let sequence = source.info();
+                let value = source.next_with(|rng| sampler.sample(rng));
+                let event = Event::default()
+                    .id(sequence.index.to_string())
+                    .json_data(RandomResponse { value, sequence })
+                    .expect("random response serializes to JSON");
+                Some((Ok(event), (source, interval, remaining - 1)))
+            }
+        },
+    );
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+fn single_response(
+    value: Value,
+    sequence: SequenceInfo,
+    format: Format,
+) -> Result<Response, ApiError> {
+    let internal = |err: &dyn std::fmt::Display| {
+        ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal_error",
+            format!("failed to render response: {err}"),
+        )
+    };
+    let response = match format {
+        Format::Html => {
+            let page = RandomTemplate {
+                value,
+                seed: sequence.seed,
+                stream: sequence.stream,
+                index: sequence.index,
+            };
+            Html(page.render().map_err(|err| internal(&err))?).into_response()
+        }
+        Format::Text => (
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            format!("{value}\n"),
+        )
+            .into_response(),
+        Format::Cbor => {
+            let mut body = Vec::new();
+            ciborium::into_writer(&RandomResponse { value, sequence }, &mut body)
+                .map_err(|err| internal(&err))?;
+            ([(header::CONTENT_TYPE, Format::Cbor.media_type())], body).into_response()
+        }
+        _ => Json(RandomResponse { value, sequence }).into_response(),
+    };
+    Ok(with_sequence_headers(response, sequence))
+}
+
+fn with_sequence_headers(mut response: Response, sequence: SequenceInfo) -> Response {
+    let headers = response.headers_mut();
+    headers.insert(
+        HeaderName::from_static("x-random-seed"),
+        sequence.seed.into(),
+    );
+    headers.insert(
+        HeaderName::from_static("x-random-stream"),
+        sequence.stream.into(),
+    );
+    headers.insert(
+        HeaderName::from_static("x-random-index"),
+        sequence.index.into(),
+    );
+    response
+}
+
+/// Stream `count` values in chunks so the batch is never held in memory.
+fn batch_response(sampler: Sampler, source: Source, count: u64, format: Format) -> Response {
+    let sequence = source.info();
+    let header = (format == Format::Csv).then(|| CSV_HEADER.to_string());
+    let mut records = source
+        .values(sampler, count)
+        .map(move |(index, value)| match format {
+            Format::Csv => csv_record(index, value),
+            _ => ndjson_record(index, value),
+        });
+    let chunks = std::iter::from_fn(move || {
+        let chunk: String = records.by_ref().take(CHUNK_SIZE).collect();
+        (!chunk.is_empty()).then_some(chunk)
+    });
+    let body = Body::from_stream(stream::iter(
+        header.into_iter().chain(chunks).map(Ok::<_, Infallible>),
+    ));
+
+    let content_type = match format {
+        Format::Csv => "text/csv; charset=utf-8",
+        _ => "application/x-ndjson",
+    };
+    with_sequence_headers(
+        ([(header::CONTENT_TYPE, content_type)], body).into_response(),
+        sequence,
+    )
+}
@@ -0,0 +1,472 @@
+//! Per-client token-bucket rate limiting with daily quotas.
+//!
+//! Clients are identified by their `x-api-key` header when it holds one of
+//! the configured keys and by their IP address otherwise, so inventing keys
+//! does not buy fresh buckets. Every client gets one bucket per configured
+//! route prefix; the longest matching prefix applies and unmatched paths share
+//! the default bucket. Daily quotas count admitted requests per client across
+//! all routes and reset at midnight UTC. They can be snapshotted to disk so a
+//! restart does not hand out a fresh quota.
+//!
+//! At most `max_clients` buckets and quota entries are kept. Once full, new
+//! clients share a single overflow bucket and quota until idle buckets are
+//! purged or the day rolls over.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use dashmap::DashMap;
+use data_encoding::HEXLOWER;
+use serde::{Deserialize, Serialize};
+use server_core::shutdown::Shutdown;
+use sha2::{Digest, Sha256};
+
+use crate::error::ApiError;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Client id shared by everyone once the limiter is tracking `max_clients`.
+const OVERFLOW_CLIENT: &str = "overflow";
+
+/// Bucket size and refill rate for one route prefix.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limit {
+    /// Requests a client may make in a burst.
+    pub burst: u32,
+    /// Tokens added back per second.
+    pub per_second: f64,
+}
+
+impl Limit {
+    /// Seconds until an empty bucket is full again, used as the policy window.
+    fn window_secs(&self) -> u64 {
+        (f64::from(self.burst) / self.per_second).ceil() as u64
+    }
+}
+
+/// A `PREFIX=BURST:PER_SECOND` rule given on the command line, e.g.
+/// `/selftest=5:0.1`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteLimit {
+    pub prefix: String,
+    pub limit: Limit,
+}
+
+impl FromStr for RouteLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (prefix, limit) = s
+            .split_once('=')
+            .ok_or("expected PREFIX=BURST:PER_SECOND")?;
+        if !prefix.starts_with('/') {
+            return Err(format!("route prefix `{prefix}` must start with `/`"));
+        }
+        let (burst, per_second) = limit
+            .split_once(':')
+            .ok_or("expected PREFIX=BURST:PER_SECOND")?;
+        let burst: u32 = burst
+            .parse()
+            .map_err(|_| format!("burst `{burst}` is not a whole number"))?;
+        let per_second: f64 = per_second
+            .parse()
+            .map_err(|_| format!("rate `{per_second}` is not a number"))?;
+        if burst == 0 {
+            return Err("burst must be at least 1".into());
+        }
+        if !(per_second.is_finite() && per_second > 0.0) {
+            return Err("rate must be a positive number".into());
+        }
+        Ok(RouteLimit {
+            prefix: prefix.trim_end_matches('/').to_string(),
+            limit: Limit { burst, per_second },
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// Limit for paths that match none of `routes`.
+    pub default: Limit,
+    pub routes: Vec<RouteLimit>,
+    /// Requests each client may make per UTC day; `None` disables quotas.
+    pub daily_quota: Option<u64>,
+    pub snapshot: Option<PathBuf>,
+    /// `x-api-key` values that get their own buckets and quota; any other key
+    /// is limited by IP address like a request without one.
+    pub api_keys: Vec<String>,
+    /// Most buckets, and most quota entries, kept at once.
+    pub max_clients: usize,
+}
+
+impl RateLimitConfig {
+    /// Add `rule`, replacing any existing rule for the same prefix. A rule for
+    /// `/` applies to every path without a more specific rule.
+    pub fn set_route(&mut self, rule: RouteLimit) {
+        self.routes.retain(|r| r.prefix != rule.prefix);
+        self.routes.push(rule);
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            default: Limit {
+                burst: 100,
+                per_second: 20.0,
+            },
+            // Self-tests draw up to a million samples per request.
+            routes: vec![RouteLimit {
+                prefix: "/selftest".into(),
+                limit: Limit {
+                    burst: 5,
+                    per_second: 0.1,
+                },
+            }],
+            daily_quota: None,
+            snapshot: None,
+            api_keys: Vec::new(),
+            max_clients: 100_000,
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Outcome of taking a token from a bucket.
+struct Decision {
+    limit: Limit,
+    allowed: bool,
+    remaining: u32,
+    /// Seconds until the bucket is full again.
+    reset_secs: u64,
+    /// Seconds until the next token is available; only set when rejected.
+    retry_after_secs: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct QuotaSnapshot {
+    day: u64,
+    used: HashMap<String, u64>,
+}
+
+struct Quotas {
+    day: u64,
+    used: HashMap<String, u64>,
+}
+
+/// Shared limiter state; cheap to clone.
+#[derive(Clone)]
+pub struct RateLimiter {
+    config: Arc<RateLimitConfig>,
+    /// Client ids of `config.api_keys`.
+    known_keys: Arc<HashSet<String>>,
+    buckets: Arc<DashMap<(String, usize), Bucket>>,
+    quotas: Arc<Mutex<Quotas>>,
+}
+
+impl RateLimiter {
+    /// Build a limiter, restoring today's quota usage from the snapshot file
+    /// if there is one.
+    pub fn new(mut config: RateLimitConfig) -> io::Result<Self> {
+        // Longest prefix first, so the first match is the most specific.
+        config
+            .routes
+            .sort_by_key(|r| std::cmp::Reverse(r.prefix.len()));
+        let today = current_day();
+        let used = match &config.snapshot {
+            Some(path) => match load_snapshot(path)? {
+                Some(snapshot) if snapshot.day == today => snapshot.used,
+                _ => HashMap::new(),
+            },
+            None => HashMap::new(),
+        };
+        let known_keys = config
+            .api_keys
+            .iter()
+            .map(|key| key_id(key.as_bytes()))
+            .collect();
+        Ok(RateLimiter {
+            known_keys: Arc::new(known_keys),
+            config: Arc::new(config),
+            buckets: Arc::default(),
+            quotas: Arc::new(Mutex::new(Quotas { day: today, used })),
+        })
+    }
+
+    /// Index into `config.routes` of the rule for `path`, or `routes.len()`
+    /// for the default limit.
+    fn route(&self, path: &str) -> (usize, Limit) {
+        self.config
+            .routes
+            .iter()
+            .position(|r| {
+                path.strip_prefix(r.prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+            .map_or((self.config.routes.len(), self.config.default), |i| {
+                (i, self.config.routes[i].limit)
+            })
+    }
+
+    /// Identifies a client: by API key if it is a configured one, otherwise by
+    /// IP address.
+    fn client_id(&self, headers: &HeaderMap, addr: Option<SocketAddr>) -> String {
+        if let Some(key) = headers.get("x-api-key").map(HeaderValue::as_bytes) {
+            let id = key_id(key);
+            if self.known_keys.contains(&id) {
+                return id;
+            }
+        }
+        match addr {
+            Some(addr) => format!("ip:{}", addr.ip()),
+            None => "ip:unknown".to_string(),
+        }
+    }
+
+    fn take(&self, client: &str, path: &str) -> Decision {
+        let (route, limit) = self.route(path);
+        let now = Instant::now();
+        let burst = f64::from(limit.burst);
+        let mut key = (client.to_string(), route);
+        if self.buckets.len() >= self.config.max_clients && !self.buckets.contains_key(&key) {
+            key.0 = OVERFLOW_CLIENT.to_string();
+        }
+        let mut bucket = self.buckets.entry(key).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limit.per_second).min(burst);
+        bucket.updated = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+        Decision {
+            limit,
+            allowed,
+            remaining: bucket.tokens.floor() as u32,
+            reset_secs: ((burst - bucket.tokens) / limit.per_second).ceil() as u64,
+            retry_after_secs: if allowed {
+                0
+            } else {
+                ((1.0 - bucket.tokens) / limit.per_second).ceil() as u64
+            },
+        }
+    }
+
+    /// Count one request against the client's daily quota. Returns the
+    /// requests left today, or `Err` with the seconds until the quota resets.
+    fn charge_quota(&self, client: &str) -> Result<Option<u64>, u64> {
+        let Some(quota) = self.config.daily_quota else {
+            return Ok(None);
+        };
+        let today = current_day();
+        let mut quotas = self.quotas.lock().unwrap();
+        if quotas.day != today {
+            quotas.day = today;
+            quotas.used.clear();
+        }
+        let client =
+            if quotas.used.len() >= self.config.max_clients && !quotas.used.contains_key(client) {
+                OVERFLOW_CLIENT
+            } else {
+                client
+            };
+        let used = quotas.used.entry(client.to_string()).or_insert(0);
+        if *used >= quota {
+            return Err(seconds_until_midnight());
+        }
+        *used += 1;
+        Ok(Some(quota - *used))
+    }
+
+    /// Write today's quota usage to the snapshot file, if one is configured.
+    pub fn save_snapshot(&self) -> io::Result<()> {
+        let Some(path) = &self.config.snapshot else {
+            return Ok(());
+        };
+        let snapshot = {
+            let quotas = self.quotas.lock().unwrap();
+            QuotaSnapshot {
+                day: quotas.day,
+                used: quotas.used.clone(),
+            }
+        };
+        // Write then rename so a crash never leaves a truncated snapshot.
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&snapshot)?)?;
+        std::fs::rename(&tmp, path)
+    }
+
+    /// Drop buckets that have been idle long enough to be full again; a
+    /// fresh bucket behaves the same.
+    pub fn purge_idle(&self) -> usize {
+        let now = Instant::now();
+        let before = self.buckets.len();
+        self.buckets.retain(|&(_, route), bucket| {
+            let limit = self
+                .config
+                .routes
+                .get(route)
+                .map_or(self.config.default, |r| r.limit);
+            let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+            bucket.tokens + elapsed * limit.per_second < f64::from(limit.burst)
+        });
+        before - self.buckets.len()
+    }
+
+    /// Periodically purge idle buckets and snapshot quotas in the background,
+    /// taking a final snapshot at shutdown.
+    pub fn spawn_sweeper(
+        &self,
+        every: Duration,
+        shutdown: &Shutdown,
+    ) -> tokio::task::JoinHandle<()> {
+        let limiter = self.clone();
+        let token = shutdown.token();
+        shutdown.spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                let stopping = tokio::select! {
+                    _ = interval.tick() => false,
+                    () = token.cancelled() => true,
+                };
+                limiter.purge_idle();
+                if let Err(err) = limiter.save_snapshot() {
+                    tracing::warn!(%err, "failed to save quota snapshot");
+                }
+                if stopping {
+                    break;
+                }
+            }
+        })
+    }
+}
+
+fn load_snapshot(path: &Path) -> io::Result<Option<QuotaSnapshot>> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+fn unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn current_day() -> u64 {
+    unix_secs() / SECONDS_PER_DAY
+}
+
+fn seconds_until_midnight() -> u64 {
+    SECONDS_PER_DAY - unix_secs() % SECONDS_PER_DAY
+}
+
+/// Client id for an API key. Keys are hashed so they never end up in a
+/// snapshot file.
+fn key_id(key: &[u8]) -> String {
+    let digest = Sha256::digest(key);
+    format!("key:{}", HEXLOWER.encode(&digest[..16]))
+}
+
+/// Middleware enforcing the bucket and quota for every request.
+pub async fn rate_limit(
+    State(limiter): State<RateLimiter>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let client = limiter.client_id(
+        request.headers(),
+        connect_info.map(|ConnectInfo(addr)| addr),
+    );
+    let decision = limiter.take(&client, request.uri().path());
+    if !decision.allowed {
+        let err = ApiError::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            "rate_limited",
+            format!(
+                "too many requests; retry in {} s",
+                decision.retry_after_secs
+            ),
+        );
+        return limited(err, &decision, decision.retry_after_secs);
+    }
+    let quota_remaining = match limiter.charge_quota(&client) {
+        Ok(remaining) => remaining,
+        Err(reset) => {
+            let err = ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "quota_exceeded",
+                "daily request quota exhausted; it resets at midnight UTC",
+            );
+            return limited(err, &decision, reset);
+        }
+    };
+
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    set_rate_limit_headers(headers, &decision);
+    if let Some(remaining) = quota_remaining {
+        headers.insert(
+            HeaderName::from_static("x-quota-remaining"),
+            remaining.into(),
+        );
+    }
+    response
+}
+
+fn limited(err: ApiError, decision: &Decision, retry_after_secs: u64) -> Response {
+    let mut response = err.into_response();
+    let headers = response.headers_mut();
+    set_rate_limit_headers(headers, decision);
+    headers.insert(header::RETRY_AFTER, retry_after_secs.into());
+    response
+}
+
+/// `RateLimit-*` headers from the IETF `draft-ietf-httpapi-ratelimit-headers`.
+fn set_rate_limit_headers(headers: &mut HeaderMap, decision: &Decision) {
+    let policy = format!(
+        "{};w={}",
+        decision.limit.burst,
+        decision.limit.window_secs()
+    );
+    headers.insert(
+        HeaderName::from_static("ratelimit-limit"),
+        decision.limit.burst.into(),
+    );
+    headers.insert(
+        HeaderName::from_static("ratelimit-remaining"),
+        decision.remaining.into(),
+    );
+    headers.insert(
+        HeaderName::from_static("ratelimit-reset"),
+        decision.reset_secs.into(),
+    );
+    if let Ok(policy) = HeaderValue::from_str(&policy) {
+        headers.insert(HeaderName::from_static("ratelimit-policy"), policy);
+    }
+}
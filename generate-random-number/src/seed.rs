@@ -0,0 +1,119 @@
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::generator::{ParamError, Sampler, Value};
+
+/// Largest `offset` accepted; skipping is done by drawing and discarding values.
+pub const MAX_OFFSET: u64 = 1_000_000;
+
+/// Most random draws spent skipping to `offset`, so results that each take many
+/// draws get a proportionally smaller offset limit.
+pub const MAX_SKIP_DRAWS: u64 = 10_000_000;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SeedParameters {
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub stream: u64,
+    #[serde(default)]
+    pub offset: u64,
+}
+
+/// Where a response sits in a reproducible sequence. Replaying a request with
+/// the same `seed`, `stream` and `offset = index` gives the same values.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SequenceInfo {
+    pub seed: u64,
+    pub stream: u64,
+    pub index: u64,
+}
+
+/// A seedable random source that counts how many results it has produced.
+///
+/// Requests without a seed still go through here with a freshly drawn seed, so
+/// every response can be replayed.
+pub struct Source {
+    seed: u64,
+    stream: u64,
+    index: u64,
+    rng: ChaCha8Rng,
+}
+
+impl Source {
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        rng.set_stream(stream);
+        Source {
+            seed,
+            stream,
+            index: 0,
+            rng,
+        }
+    }
+
+    /// Build a source from request parameters and move it to `offset` by
+    /// discarding that many results of `f`.
+    pub fn from_parameters<T>(
+        params: &SeedParameters,
+        f: impl FnMut(&mut ChaCha8Rng) -> T,
+    ) -> Result<Self, ParamError> {
+        Source::from_parameters_with_cost(params, 1, f)
+    }
+
+    /// Like [`Source::from_parameters`] for results of `f` that each take
+    /// about `draws` random draws; `offset` is limited so that skipping stays
+    /// within [`MAX_SKIP_DRAWS`].
+    pub fn from_parameters_with_cost<T>(
+        params: &SeedParameters,
+        draws: u64,
+        f: impl FnMut(&mut ChaCha8Rng) -> T,
+    ) -> Result<Self, ParamError> {
+        let max_offset = MAX_OFFSET.min(MAX_SKIP_DRAWS / draws.max(1));
+        if params.offset > max_offset {
+            let reason = if max_offset < MAX_OFFSET {
+                " for a result this large"
+            } else {
+                ""
+            };
+            return Err(ParamError::new(
+                "offset",
+                format!("must not exceed {max_offset}{reason}"),
+            ));
+        }
+        let seed = params.seed.unwrap_or_else(|| thread_rng().gen());
+        let mut source = Source::new(seed, params.stream);
+        source.skip_with(params.offset, f);
+        Ok(source)
+    }
+
+    /// Current position, i.e. the index the next result will have.
+    pub fn info(&self) -> SequenceInfo {
+        SequenceInfo {
+            seed: self.seed,
+            stream: self.stream,
+            index: self.index,
+        }
+    }
+
+    /// Produce the next result of the sequence with `f`.
+    pub fn next_with<T>(&mut self, f: impl FnOnce(&mut ChaCha8Rng) -> T) -> T {
+        self.index += 1;
+        f(&mut self.rng)
+    }
+
+    /// Advance past `n` results produced by `f`, discarding them.
+    pub fn skip_with<T>(&mut self, n: u64, mut f: impl FnMut(&mut ChaCha8Rng) -> T) {
+        for _ in 0..n {
+            self.next_with(&mut f);
+        }
+    }
+
+    /// The next `count` values of `sampler`, each with its sequence index.
+    pub fn values(mut self, sampler: Sampler, count: u64) -> impl Iterator<Item = (u64, Value)> {
+        (0..count).map(move |_| {
+            let index = self.index;
+            (index, self.next_with(|rng| sampler.sample(rng)))
+        })
+    }
+}
@@ -0,0 +1,389 @@
+use axum::{
+    extract::{rejection::QueryRejection, Query},
+    http::StatusCode,
+    Json,
+};
+use clap::ValueEnum;
+use rand::{rngs::OsRng, rngs::StdRng, thread_rng, Rng, RngCore, SeedableRng};
+use rand_chacha::{ChaCha20Rng, ChaCha8Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::distribution::Distribution;
+use crate::error::ApiError;
+use crate::generator::{ParamError, Sampler, Value};
+use crate::random::SamplerParameters;
+use crate::stats::{self, ln_gamma, normal_cdf, TestResult};
+
+pub const MIN_SAMPLES: usize = 100;
+pub const MAX_SAMPLES: usize = 1_000_000;
+
+/// The random number generators the self-test can exercise. `chacha8` is the
+/// generator behind every seeded endpoint.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum GeneratorKind {
+    #[default]
+    Chacha8,
+    Chacha20,
+    Std,
+    Thread,
+    Os,
+}
+
+impl GeneratorKind {
+    fn seeded(self) -> bool {
+        !matches!(self, GeneratorKind::Thread | GeneratorKind::Os)
+    }
+
+    fn rng(self, seed: u64) -> Box<dyn RngCore> {
+        match self {
+            GeneratorKind::Chacha8 => Box::new(ChaCha8Rng::seed_from_u64(seed)),
+            GeneratorKind::Chacha20 => Box::new(ChaCha20Rng::seed_from_u64(seed)),
+            GeneratorKind::Std => Box::new(StdRng::seed_from_u64(seed)),
+            GeneratorKind::Thread => Box::new(thread_rng()),
+            GeneratorKind::Os => Box::new(OsRng),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SelftestParameters {
+    #[serde(default)]
+    pub generator: GeneratorKind,
+    #[serde(default = "default_samples")]
+    pub samples: usize,
+    pub seed: Option<u64>,
+    #[serde(default = "default_alpha")]
+    pub alpha: f64,
+}
+
+fn default_samples() -> usize {
+    10_000
+}
+
+fn default_alpha() -> f64 {
+    0.01
+}
+
+#[derive(Debug, Serialize)]
+pub struct TestReport {
+    pub name: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub statistic: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p_value: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub passed: Option<bool>,
+    /// Why the test could not be applied to this generator and distribution.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub generator: GeneratorKind,
+    pub samples: usize,
+    pub seed: Option<u64>,
+    pub alpha: f64,
+    pub passed: bool,
+    pub tests: Vec<TestReport>,
+}
+
+/// What the sampler is supposed to produce.
+enum Model {
+    /// Continuous with the given CDF.
+    Continuous(Box<dyn Fn(f64) -> f64>),
+    /// Discrete over bins with the given probabilities.
+    Discrete {
+        probabilities: Vec<f64>,
+        bin: Box<dyn Fn(i64) -> usize>,
+    },
+    /// Every sample is the same value; nothing to test.
+    Degenerate,
+    /// Too many distinct values to bin reasonably.
+    Unsupported,
+}
+
+fn model(sampler: &Sampler) -> Model {
+    match *sampler {
+        Sampler::Int {
+            start,
+            end,
+            inclusive,
+        } => {
+            let size = (i128::from(end) - i128::from(start) + i128::from(inclusive)) as u128;
+            if size <= 1 {
+                Model::Degenerate
+            } else if size <= 1000 {
+                Model::Discrete {
+                    probabilities: vec![1.0 / size as f64; size as usize],
+                    bin: Box::new(move |v| (i128::from(v) - i128::from(start)) as usize),
+                }
+            } else {
+                // 100 buckets of (almost) equal width.
+                let ceil_div = |a: u128, b: u128| a.div_ceil(b);
+                let probabilities = (0..100u128)
+                    .map(|i| {
+                        (ceil_div((i + 1) * size, 100) - ceil_div(i * size, 100)) as f64
+                            / size as f64
+                    })
+                    .collect();
+                Model::Discrete {
+                    probabilities,
+                    bin: Box::new(move |v| {
+                        ((i128::from(v) - i128::from(start)) as u128 * 100 / size) as usize
+                    }),
+                }
+            }
+        }
+        Sampler::Float { start, end, .. } if start == end => Model::Degenerate,
+        Sampler::Float { start, end, .. } => Model::Continuous(Box::new(move |x| {
+            ((x - start) / (end - start)).clamp(0.0, 1.0)
+        })),
+        Sampler::Bool { p } if p == 0.0 || p == 1.0 => Model::Degenerate,
+        Sampler::Bool { p } => Model::Discrete {
+            probabilities: vec![1.0 - p, p],
+            bin: Box::new(|v| v as usize),
+        },
+        Sampler::Dist(ref dist) => match *dist {
+            Distribution::Normal { stddev, .. } | Distribution::LogNormal { stddev, .. }
+                if stddev == 0.0 =>
+            {
+                Model::Degenerate
+            }
+            Distribution::Normal { mean, stddev, .. } => {
+                Model::Continuous(Box::new(move |x| normal_cdf((x - mean) / stddev)))
+            }
+            Distribution::LogNormal { mean, stddev, .. } => Model::Continuous(Box::new(move |x| {
+                if x <= 0.0 {
+                    0.0
+                } else {
+                    normal_cdf((x.ln() - mean) / stddev)
+                }
+            })),
+            Distribution::Exponential { lambda, .. } => {
+                Model::Continuous(Box::new(move |x| (1.0 - (-lambda * x).exp()).max(0.0)))
+            }
+            Distribution::Poisson { lambda, .. } => {
+                let spread = 10.0 * lambda.sqrt() + 5.0;
+                integer_model(
+                    (lambda - spread).floor().max(0.0) as i64,
+                    (lambda + spread).ceil() as i64,
+                    move |k| k as f64 * lambda.ln() - lambda - ln_gamma(k as f64 + 1.0),
+                )
+            }
+            Distribution::Binomial { n, p, .. } if n == 0 || p == 0.0 || p == 1.0 => {
+                Model::Degenerate
+            }
+            Distribution::Binomial { n, p, .. } => {
+                let n = n as f64;
+                let mean = n * p;
+                let spread = 10.0 * (n * p * (1.0 - p)).sqrt() + 5.0;
+                integer_model(
+                    (mean - spread).floor().max(0.0) as i64,
+                    (mean + spread).ceil().min(n) as i64,
+                    move |k| {
+                        let k = k as f64;
+                        ln_gamma(n + 1.0) - ln_gamma(k + 1.0) - ln_gamma(n - k + 1.0)
+                            + k * p.ln()
+                            + (n - k) * (1.0 - p).ln()
+                    },
+                )
+            }
+            Distribution::Categorical { ref weights, .. } => {
+                if weights.iter().filter(|&&w| w > 0.0).count() < 2 {
+                    return Model::Degenerate;
+                }
+                let total: f64 = weights.iter().sum();
+                Model::Discrete {
+                    probabilities: weights.iter().map(|w| w / total).collect(),
+                    bin: Box::new(|v| v as usize),
+                }
+            }
+        },
+    }
+}
+
+/// Bins for the integers `lo..=hi` with the given log-pmf, grouped so there
+/// are at most 1000 of them, plus one bin for everything outside the range.
+fn integer_model(lo: i64, hi: i64, log_pmf: impl Fn(i64) -> f64) -> Model {
+    let width = (hi - lo + 1) as u64;
+    if width > 1_000_000 {
+        return Model::Unsupported;
+    }
+    let group = width.div_ceil(1000) as i64;
+    let groups = (width as i64 + group - 1) / group;
+    let mut probabilities = vec![0.0; groups as usize + 1];
+    for k in lo..=hi {
+        probabilities[((k - lo) / group) as usize] += log_pmf(k).exp();
+    }
+    let inside: f64 = probabilities.iter().sum();
+    probabilities[groups as usize] = (1.0 - inside).max(0.0);
+    Model::Discrete {
+        probabilities,
+        bin: Box::new(move |k| {
+            if (lo..=hi).contains(&k) {
+                ((k - lo) / group) as usize
+            } else {
+                groups as usize
+            }
+        }),
+    }
+}
+
+impl SelftestParameters {
+    pub fn validate(&self) -> Result<(), ParamError> {
+        if !(MIN_SAMPLES..=MAX_SAMPLES).contains(&self.samples) {
+            return Err(ParamError::new(
+                "samples",
+                format!("must be between {MIN_SAMPLES} and {MAX_SAMPLES}"),
+            ));
+        }
+        if !(self.alpha > 0.0 && self.alpha < 1.0) {
+            return Err(ParamError::new("alpha", "must be between 0 and 1"));
+        }
+        if self.seed.is_some() && !self.generator.seeded() {
+            return Err(ParamError::new(
+                "seed",
+                "the thread and os generators cannot be seeded",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Draw `params.samples` values and run chi-square, Kolmogorov–Smirnov, runs
+/// and serial-correlation tests on them.
+pub fn run(sampler: &Sampler, params: &SelftestParameters) -> Report {
+    let seed = params
+        .generator
+        .seeded()
+        .then(|| params.seed.unwrap_or_else(|| thread_rng().gen()));
+    let mut rng = params.generator.rng(seed.unwrap_or_default());
+    let values: Vec<Value> = (0..params.samples)
+        .map(|_| sampler.sample(&mut rng))
+        .collect();
+    let numbers: Vec<f64> = values
+        .iter()
+        .map(|v| match *v {
+            Value::Int(i) => i as f64,
+            Value::Float(f) => f,
+            Value::Bool(b) => f64::from(u8::from(b)),
+        })
+        .collect();
+
+    let report = |name, result: Result<Option<TestResult>, &'static str>| match result {
+        Ok(Some(r)) => TestReport {
+            name,
+            statistic: Some(r.statistic),
+            p_value: Some(r.p_value),
+            passed: Some(r.p_value >= params.alpha),
+            skipped: None,
+        },
+        Ok(None) => report_skipped(name, "not enough variation in the sample"),
+        Err(reason) => report_skipped(name, reason),
+    };
+
+    let model = model(sampler);
+    let (chi_square, ks) = match &model {
+        Model::Continuous(cdf) => {
+            let u: Vec<f64> = numbers.iter().map(|&x| cdf(x)).collect();
+            let bins = (params.samples / 50).clamp(10, 100);
+            let mut observed = vec![0u64; bins];
+            for &x in &u {
+                observed[((x * bins as f64) as usize).min(bins - 1)] += 1;
+            }
+            (
+                Ok(stats::chi_square(&observed, &vec![1.0 / bins as f64; bins])),
+                Ok(stats::kolmogorov_smirnov(u)),
+            )
+        }
+        Model::Discrete { probabilities, bin } => {
+            let mut observed = vec![0u64; probabilities.len()];
+            for &x in &numbers {
+                observed[bin(x as i64).min(probabilities.len() - 1)] += 1;
+            }
+            (
+                Ok(stats::chi_square(&observed, probabilities)),
+                Err("only applies to continuous distributions"),
+            )
+        }
+        Model::Degenerate => (
+            Err("the distribution only produces one value"),
+            Err("the distribution only produces one value"),
+        ),
+        Model::Unsupported => (
+            Err("too many distinct values to bin"),
+            Err("only applies to continuous distributions"),
+        ),
+    };
+
+    let symbols: Vec<bool> = if matches!(sampler, Sampler::Bool { .. }) {
+        numbers.iter().map(|&x| x > 0.5).collect()
+    } else {
+        let mut sorted = numbers.clone();
+        sorted.sort_by(f64::total_cmp);
+        let median = sorted[sorted.len() / 2];
+        numbers
+            .iter()
+            .filter(|&&x| x != median)
+            .map(|&x| x > median)
+            .collect()
+    };
+    let degenerate = matches!(model, Model::Degenerate);
+    let independence = |result| {
+        if degenerate {
+            Err("the distribution only produces one value")
+        } else {
+            Ok(result)
+        }
+    };
+
+    let tests = vec![
+        report("chi_square", chi_square),
+        report("kolmogorov_smirnov", ks),
+        report("runs", independence(stats::runs(&symbols))),
+        report(
+            "serial_correlation",
+            independence(stats::serial_correlation(&numbers)),
+        ),
+    ];
+    Report {
+        generator: params.generator,
+        samples: params.samples,
+        seed,
+        alpha: params.alpha,
+        passed: tests.iter().all(|t| t.passed != Some(false)),
+        tests,
+    }
+}
+
+fn report_skipped(name: &'static str, reason: &'static str) -> TestReport {
+    TestReport {
+        name,
+        statistic: None,
+        p_value: None,
+        passed: None,
+        skipped: Some(reason),
+    }
+}
+
+/// `GET /selftest/{int,float,bool,dist}?samples=100000&generator=chacha8`
+pub async fn selftest_handler<P: SamplerParameters>(
+    params: Result<Query<P>, QueryRejection>,
+    selftest: Result<Query<SelftestParameters>, QueryRejection>,
+) -> Result<Json<Report>, ApiError> {
+    let Query(params) = params?;
+    let Query(selftest) = selftest?;
+    let sampler = params.sampler()?;
+    selftest.validate()?;
+    tokio::task::spawn_blocking(move || Json(run(&sampler, &selftest)))
+        .await
+        .map_err(|_| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internal",
+                "the self-test did not complete",
+            )
+        })
+}
@@ -0,0 +1,379 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        Path, Query, State,
+    },
+    http::StatusCode,
+    Json,
+};
+use dashmap::{mapref::entry::Entry, DashMap};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use server_core::shutdown::Shutdown;
+
+use crate::error::ApiError;
+use crate::generator::ParamError;
+use crate::seed::{SeedParameters, SequenceInfo, Source};
+
+pub const DEFAULT_TTL_SECS: u64 = 3600;
+pub const MAX_TTL_SECS: u64 = 7 * 24 * 3600;
+pub const MAX_SESSIONS: usize = 10_000;
+pub const MAX_DRAW: u64 = 10_000;
+
+/// The population of a draw session: an integer range or a list of items.
+#[derive(Debug, Clone)]
+pub enum Pool {
+    Range { start: i64, len: u64 },
+    Items(Vec<JsonValue>),
+}
+
+impl Pool {
+    fn len(&self) -> u64 {
+        match self {
+            Pool::Range { len, .. } => *len,
+            Pool::Items(items) => items.len() as u64,
+        }
+    }
+
+    fn get(&self, position: u64) -> JsonValue {
+        match self {
+            Pool::Range { start, .. } => JsonValue::from(start.wrapping_add(position as i64)),
+            Pool::Items(items) => items[position as usize].clone(),
+        }
+    }
+}
+
+/// Fisher–Yates shuffle performed one draw at a time. Only positions that have
+/// been swapped are stored, so a session over a huge range costs memory in
+/// proportion to the number of draws, not the size of the range.
+#[derive(Debug, Clone)]
+struct Deck {
+    remaining: u64,
+    swapped: HashMap<u64, u64>,
+}
+
+impl Deck {
+    fn new(len: u64) -> Self {
+        Deck {
+            remaining: len,
+            swapped: HashMap::new(),
+        }
+    }
+
+    fn draw<R: Rng + ?Sized>(&mut self, rng: &mut R) -> u64 {
+        let j = rng.gen_range(0..self.remaining);
+        let last = self.remaining - 1;
+        let picked = self.swapped.get(&j).copied().unwrap_or(j);
+        let tail = self.swapped.remove(&last).unwrap_or(last);
+        if j != last {
+            self.swapped.insert(j, tail);
+        }
+        self.remaining = last;
+        picked
+    }
+}
+
+pub struct Session {
+    pool: Pool,
+    deck: Deck,
+    drawn: Vec<u64>,
+    source: Source,
+    ttl: Duration,
+    expires_at: Instant,
+}
+
+impl Session {
+    fn touch(&mut self) {
+        self.expires_at = Instant::now() + self.ttl;
+    }
+
+    fn info(&self, name: &str) -> SessionInfo {
+        SessionInfo {
+            name: name.to_string(),
+            total: self.pool.len(),
+            remaining: self.deck.remaining,
+            drawn: self.drawn.iter().map(|&p| self.pool.get(p)).collect(),
+            ttl_secs: self.ttl.as_secs(),
+            expires_in_secs: self
+                .expires_at
+                .saturating_duration_since(Instant::now())
+                .as_secs(),
+            sequence: self.source.info(),
+        }
+    }
+}
+
+/// Concurrent in-memory store of named draw sessions. Sessions expire after
+/// `ttl_secs` without being used.
+#[derive(Clone, Default)]
+pub struct SessionStore {
+    sessions: Arc<DashMap<String, Session>>,
+}
+
+impl SessionStore {
+    /// Drop every expired session; returns how many were removed.
+    pub fn purge_expired(&self) -> usize {
+        let now = Instant::now();
+        let before = self.sessions.len();
+        self.sessions.retain(|_, session| session.expires_at > now);
+        before - self.sessions.len()
+    }
+
+    /// Periodically purge expired sessions in the background until shutdown.
+    pub fn spawn_sweeper(
+        &self,
+        every: Duration,
+        shutdown: &Shutdown,
+    ) -> tokio::task::JoinHandle<()> {
+        let store = self.clone();
+        let token = shutdown.token();
+        shutdown.spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    () = token.cancelled() => break,
+                }
+                store.purge_expired();
+            }
+        })
+    }
+
+    fn with_session<T>(
+        &self,
+        name: &str,
+        f: impl FnOnce(&mut Session) -> Result<T, ApiError>,
+    ) -> Result<T, ApiError> {
+        let not_found = || ApiError::not_found(format!("no draw session named `{name}`"));
+        let mut session = self.sessions.get_mut(name).ok_or_else(not_found)?;
+        if session.expires_at <= Instant::now() {
+            drop(session);
+            self.sessions.remove(name);
+            return Err(not_found());
+        }
+        session.touch();
+        f(&mut session)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateSession {
+    name: String,
+    start: Option<i64>,
+    end: Option<i64>,
+    #[serde(default)]
+    inclusive: bool,
+    items: Option<Vec<JsonValue>>,
+    ttl_secs: Option<u64>,
+    #[serde(flatten)]
+    seed: SeedParameters,
+}
+
+#[derive(Deserialize)]
+pub struct DrawParameters {
+    #[serde(default = "default_draw_count")]
+    count: u64,
+}
+
+fn default_draw_count() -> u64 {
+    1
+}
+
+#[derive(Serialize)]
+pub struct SessionInfo {
+    name: String,
+    total: u64,
+    remaining: u64,
+    drawn: Vec<JsonValue>,
+    ttl_secs: u64,
+    expires_in_secs: u64,
+    #[serde(flatten)]
+    sequence: SequenceInfo,
+}
+
+#[derive(Serialize)]
+pub struct DrawResponse {
+    values: Vec<JsonValue>,
+    remaining: u64,
+    #[serde(flatten)]
+    sequence: SequenceInfo,
+}
+
+/// Sessions always start at the beginning of their sequence; an `offset` would
+/// have to consume values from the pool, so it is rejected instead.
+fn session_source(seed: &SeedParameters) -> Result<Source, ParamError> {
+    if seed.offset != 0 {
+        return Err(ParamError::new(
+            "offset",
+            "is not supported for draw sessions",
+        ));
+    }
+    Source::from_parameters(seed, |_| ())
+}
+
+fn pool_from(request: &mut CreateSession) -> Result<Pool, ParamError> {
+    match (request.start, request.end, request.items.take()) {
+        (Some(start), Some(end), None) => {
+            let len = i128::from(end) - i128::from(start) + i128::from(request.inclusive);
+            if len <= 0 {
+                return Err(ParamError::new(
+                    "end",
+                    "range must contain at least one value",
+                ));
+            }
+            let len =
+                u64::try_from(len).map_err(|_| ParamError::new("end", "range is too large"))?;
+            Ok(Pool::Range { start, len })
+        }
+        (None, None, Some(items)) if items.is_empty() => {
+            Err(ParamError::new("items", "must not be empty"))
+        }
+        (None, None, Some(items)) => Ok(Pool::Items(items)),
+        (None, None, None) => Err(ParamError::new(
+            "items",
+            "either items or start and end are required",
+        )),
+        (_, _, Some(_)) => Err(ParamError::new(
+            "items",
+            "cannot be combined with start and end",
+        )),
+        (None, Some(_), None) => Err(ParamError::new("start", "is required with end")),
+        (Some(_), None, None) => Err(ParamError::new("end", "is required with start")),
+    }
+}
+
+/// `POST /sessions` with `{"name": "raffle", "items": [...]}` or
+/// `{"name": "raffle", "start": 1, "end": 100}`.
+pub async fn create_handler(
+    State(store): State<SessionStore>,
+    request: Result<Json<CreateSession>, JsonRejection>,
+) -> Result<(StatusCode, Json<SessionInfo>), ApiError> {
+    let Json(mut request) = request?;
+    let name = request.name.clone();
+    if name.is_empty()
+        || name.len() > 64
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(ParamError::new("name", "must be 1-64 characters of [A-Za-z0-9_-]").into());
+    }
+    let ttl_secs = request.ttl_secs.unwrap_or(DEFAULT_TTL_SECS);
+    if !(1..=MAX_TTL_SECS).contains(&ttl_secs) {
+        return Err(
+            ParamError::new("ttl_secs", format!("must be between 1 and {MAX_TTL_SECS}")).into(),
+        );
+    }
+    let pool = pool_from(&mut request)?;
+    let deck = Deck::new(pool.len());
+    let source = session_source(&request.seed)?;
+
+    store.purge_expired();
+    if store.sessions.len() >= MAX_SESSIONS {
+        return Err(ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "too_many_sessions",
+            "the session store is full, try again later",
+        ));
+    }
+    match store.sessions.entry(name.clone()) {
+        Entry::Occupied(_) => Err(ApiError::conflict(
+            "session_exists",
+            format!("a draw session named `{name}` already exists"),
+        )),
+        Entry::Vacant(entry) => {
+            let ttl = Duration::from_secs(ttl_secs);
+            let session = entry.insert(Session {
+                pool,
+                deck,
+                drawn: Vec::new(),
+                source,
+                ttl,
+                expires_at: Instant::now() + ttl,
+            });
+            Ok((StatusCode::CREATED, Json(session.info(&name))))
+        }
+    }
+}
+
+/// `GET /sessions/:name`
+pub async fn inspect_handler(
+    State(store): State<SessionStore>,
+    Path(name): Path<String>,
+) -> Result<Json<SessionInfo>, ApiError> {
+    store.with_session(&name, |session| Ok(Json(session.info(&name))))
+}
+
+/// `POST /sessions/:name/draw?count=1`: draw values that have not been drawn yet.
+pub async fn draw_handler(
+    State(store): State<SessionStore>,
+    Path(name): Path<String>,
+    params: Result<Query<DrawParameters>, QueryRejection>,
+) -> Result<Json<DrawResponse>, ApiError> {
+    let Query(params) = params?;
+    if !(1..=MAX_DRAW).contains(&params.count) {
+        return Err(ParamError::new("count", format!("must be between 1 and {MAX_DRAW}")).into());
+    }
+    store.with_session(&name, |session| {
+        if params.count > session.deck.remaining {
+            return Err(ApiError::conflict(
+                "exhausted",
+                format!(
+                    "cannot draw {} from `{name}`, only {} left",
+                    params.count, session.deck.remaining
+                ),
+            ));
+        }
+        let sequence = session.source.info();
+        let mut values = Vec::with_capacity(params.count as usize);
+        for _ in 0..params.count {
+            let deck = &mut session.deck;
+            let position = session.source.next_with(|rng| deck.draw(rng));
+            session.drawn.push(position);
+            values.push(session.pool.get(position));
+        }
+        Ok(Json(DrawResponse {
+            values,
+            remaining: session.deck.remaining,
+            sequence,
+        }))
+    })
+}
+
+/// `POST /sessions/:name/reset`: put every value back. The session keeps its
+/// random source unless a new `seed` is given.
+pub async fn reset_handler(
+    State(store): State<SessionStore>,
+    Path(name): Path<String>,
+    seed: Result<Query<SeedParameters>, QueryRejection>,
+) -> Result<Json<SessionInfo>, ApiError> {
+    let Query(seed) = seed?;
+    store.with_session(&name, |session| {
+        session.deck = Deck::new(session.pool.len());
+        session.drawn.clear();
+        if seed.seed.is_some() {
+            session.source = session_source(&seed)?;
+        }
+        Ok(Json(session.info(&name)))
+    })
+}
+
+/// `DELETE /sessions/:name`
+pub async fn delete_handler(
+    State(store): State<SessionStore>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    match store.sessions.remove(&name) {
+        Some(_) => Ok(StatusCode::NO_CONTENT),
+        None => Err(ApiError::not_found(format!(
+            "no draw session named `{name}`"
+        ))),
+    }
+}
@@ -0,0 +1,50 @@
+use axum::extract::FromRef;
+
+use crate::fairness::FairStore;
+use crate::ratelimit::RateLimiter;
+use crate::session::SessionStore;
+use crate::static_files::StaticFiles;
+
+/// Shared state handed to every handler through axum's `State` extractor.
+#[derive(Clone)]
+pub struct AppState {
+    pub sessions: SessionStore,
+    pub fair: FairStore,
+    pub files: StaticFiles,
+    pub limiter: RateLimiter,
+}
+
+impl AppState {
+    pub fn new(files: StaticFiles, limiter: RateLimiter) -> Self {
+        AppState {
+            sessions: SessionStore::default(),
+            fair: FairStore::default(),
+            files,
+            limiter,
+        }
+    }
+}
+
+impl FromRef<AppState> for SessionStore {
+    fn from_ref(state: &AppState) -> Self {
+        state.sessions.clone()
+    }
+}
+
+impl FromRef<AppState> for FairStore {
+    fn from_ref(state: &AppState) -> Self {
+        state.fair.clone()
+    }
+}
+
+impl FromRef<AppState> for StaticFiles {
+    fn from_ref(state: &AppState) -> Self {
+        state.files.clone()
+    }
+}
+
+impl FromRef<AppState> for RateLimiter {
+    fn from_ref(state: &AppState) -> Self {
+        state.limiter.clone()
+    }
+}
@@ -0,0 +1,300 @@
+//! Serves the saved `sina.html` page together with its `sina_files/` asset
+//! directory from disk.
+//!
+//! The asset directory is not checked in, so it has to be placed next to the
+//! page under `--static-root`. Without a root the copy of the page built into
+//! the binary is served and every asset is a 404.
+//!
+//! Supports conditional requests (`ETag`/`If-None-Match`,
+//! `Last-Modified`/`If-Modified-Since`), single byte ranges and `If-Range`,
+//! and refuses any path that would leave the root directory.
+
+use std::{
+    io::SeekFrom,
+    path::{Component, Path as FsPath, PathBuf},
+    sync::{Arc, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use data_encoding::HEXLOWER;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+use crate::error::ApiError;
+
+/// Suffix Chinese-locale browsers append to assets of a saved page ("download").
+const SAVED_PAGE_SUFFIX: &str = ".下载";
+
+const PAGE: &str = "sina.html";
+
+/// The page as it was when the binary was built.
+static EMBEDDED_PAGE: &str = include_str!("../sina.html");
+
+/// Root directory the page and its assets are served from.
+#[derive(Clone)]
+pub struct StaticFiles {
+    root: Option<Arc<PathBuf>>,
+}
+
+impl StaticFiles {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        StaticFiles {
+            root: Some(Arc::new(root.into())),
+        }
+    }
+
+    /// Serve only the built-in copy of the page, with no assets.
+    pub fn embedded() -> Self {
+        StaticFiles { root: None }
+    }
+
+    /// Readiness check: the page must be present under the root.
+    pub async fn check(&self) -> Result<(), String> {
+        let Some(root) = &self.root else {
+            return Ok(());
+        };
+        match self.resolve(PAGE).await {
+            Some(_) => Ok(()),
+            None => Err(format!("{}/{PAGE} is missing", root.display())),
+        }
+    }
+
+    /// Resolve `relative` inside the root, rejecting traversal both lexically
+    /// and after following symlinks.
+    async fn resolve(&self, relative: &str) -> Option<PathBuf> {
+        if relative.contains('\\') || relative.contains('\0') {
+            return None;
+        }
+        let relative = FsPath::new(relative);
+        if !relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+        {
+            return None;
+        }
+        let root = tokio::fs::canonicalize(self.root.as_deref()?).await.ok()?;
+        let path = tokio::fs::canonicalize(root.join(relative)).await.ok()?;
+        path.starts_with(&root).then_some(path)
+    }
+}
+
+/// The `Content-Type` for `path`, looking through the saved-page suffix so
+/// that `fix.js.下载` is served as JavaScript.
+pub fn content_type(path: &FsPath) -> String {
+    let name = path.to_string_lossy();
+    let name = name.strip_suffix(SAVED_PAGE_SUFFIX).unwrap_or(&name);
+    let mime = mime_guess::from_path(name).first_or_octet_stream();
+    if mime.type_() == mime_guess::mime::TEXT || mime.subtype() == mime_guess::mime::JAVASCRIPT {
+        format!("{mime}; charset=utf-8")
+    } else {
+        mime.to_string()
+    }
+}
+
+/// `GET /` and `GET /sina.html`
+pub async fn page_handler(State(files): State<StaticFiles>, headers: HeaderMap) -> Response {
+    serve(&files, PAGE, &headers).await
+}
+
+/// `GET /sina_files/*path`
+pub async fn asset_handler(
+    State(files): State<StaticFiles>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    serve(&files, &format!("sina_files/{path}"), &headers).await
+}
+
+async fn serve(files: &StaticFiles, relative: &str, headers: &HeaderMap) -> Response {
+    match serve_file(files, relative, headers).await {
+        Ok(response) => response,
+        Err(err) => err.into_response(),
+    }
+}
+
+/// What a response body is read from.
+enum Content {
+    File(tokio::fs::File),
+    Embedded(&'static str),
+}
+
+async fn serve_file(
+    files: &StaticFiles,
+    relative: &str,
+    headers: &HeaderMap,
+) -> Result<Response, ApiError> {
+    let not_found = || ApiError::not_found(format!("`{relative}` does not exist"));
+    let (content, len, etag, modified) = if files.root.is_none() && relative == PAGE {
+        let len = EMBEDDED_PAGE.len() as u64;
+        let etag = embedded_etag().to_owned();
+        (Content::Embedded(EMBEDDED_PAGE), len, etag, None)
+    } else {
+        let path = files.resolve(relative).await.ok_or_else(not_found)?;
+        let file = tokio::fs::File::open(&path)
+            .await
+            .map_err(|_| not_found())?;
+        let metadata = file.metadata().await.map_err(|_| not_found())?;
+        if !metadata.is_file() {
+            return Err(not_found());
+        }
+        let len = metadata.len();
+        let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+        let mtime = modified
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let etag = format!("\"{len:x}-{mtime:x}\"");
+        (Content::File(file), len, etag, Some(modified))
+    };
+    let last_modified = modified.map(httpdate::fmt_http_date);
+
+    let mut response_headers = HeaderMap::new();
+    let mut set = |name, value: &str| {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            response_headers.insert(name, value);
+        }
+    };
+    set(header::ETAG, &etag);
+    if let Some(last_modified) = &last_modified {
+        set(header::LAST_MODIFIED, last_modified);
+    }
+    set(header::ACCEPT_RANGES, "bytes");
+    set(header::CONTENT_TYPE, &content_type(FsPath::new(relative)));
+    set(header::X_CONTENT_TYPE_OPTIONS, "nosniff");
+
+    if not_modified(headers, &etag, modified) {
+        return Ok((StatusCode::NOT_MODIFIED, response_headers).into_response());
+    }
+
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .filter(|_| if_range_matches(headers, &etag, last_modified.as_deref()));
+    let (status, start, length) = match range.map(|r| parse_range(r, len)) {
+        None | Some(RangeRequest::Ignored) => (StatusCode::OK, 0, len),
+        Some(RangeRequest::Satisfiable(start, end)) => {
+            set_content_range(&mut response_headers, &format!("bytes {start}-{end}/{len}"));
+            (StatusCode::PARTIAL_CONTENT, start, end - start + 1)
+        }
+        Some(RangeRequest::Unsatisfiable) => {
+            set_content_range(&mut response_headers, &format!("bytes */{len}"));
+            return Ok((StatusCode::RANGE_NOT_SATISFIABLE, response_headers).into_response());
+        }
+    };
+
+    response_headers.insert(header::CONTENT_LENGTH, HeaderValue::from(length));
+    let body = match content {
+        Content::Embedded(page) => {
+            let bytes = Bytes::from_static(page.as_bytes());
+            Body::from(bytes.slice(start as usize..(start + length) as usize))
+        }
+        Content::File(mut file) => {
+            if start > 0 {
+                file.seek(SeekFrom::Start(start))
+                    .await
+                    .map_err(|_| not_found())?;
+            }
+            Body::from_stream(ReaderStream::new(file.take(length)))
+        }
+    };
+    Ok((status, response_headers, body).into_response())
+}
+
+/// The built-in page has no modification time, so it is tagged by content.
+fn embedded_etag() -> &'static str {
+    static ETAG: OnceLock<String> = OnceLock::new();
+    ETAG.get_or_init(|| {
+        let digest = Sha256::digest(EMBEDDED_PAGE.as_bytes());
+        format!("\"{}\"", HEXLOWER.encode(&digest[..16]))
+    })
+}
+
+fn set_content_range(headers: &mut HeaderMap, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(header::CONTENT_RANGE, value);
+    }
+}
+
+/// `If-None-Match` takes precedence over `If-Modified-Since` (RFC 9110 §13.2.2).
+fn not_modified(headers: &HeaderMap, etag: &str, modified: Option<SystemTime>) -> bool {
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH) {
+        let Ok(if_none_match) = if_none_match.to_str() else {
+            return false;
+        };
+        return if_none_match
+            .split(',')
+            .map(str::trim)
+            .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag);
+    }
+    headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| httpdate::parse_http_date(v).ok())
+        .zip(modified)
+        .is_some_and(|(since, modified)| {
+            // HTTP dates have one-second resolution.
+            let secs = |t: SystemTime| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+            secs(modified) <= secs(since)
+        })
+}
+
+/// A `Range` only applies if `If-Range` is absent or still matches the file.
+fn if_range_matches(headers: &HeaderMap, etag: &str, last_modified: Option<&str>) -> bool {
+    match headers.get(header::IF_RANGE).and_then(|v| v.to_str().ok()) {
+        None => true,
+        Some(value) => value == etag || Some(value) == last_modified,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum RangeRequest {
+    /// Inclusive byte offsets.
+    Satisfiable(u64, u64),
+    Unsatisfiable,
+    /// Malformed or multi-range requests are answered with the whole file.
+    Ignored,
+}
+
+fn parse_range(value: &str, len: u64) -> RangeRequest {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return RangeRequest::Ignored;
+    };
+    if spec.contains(',') {
+        return RangeRequest::Ignored;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return RangeRequest::Ignored;
+    };
+    let (start, end) = (start.trim(), end.trim());
+    let parse = |s: &str| s.parse::<u64>().ok();
+    let (start, end) = match (start.is_empty(), end.is_empty()) {
+        // bytes=-500: the last 500 bytes.
+        (true, false) => match parse(end) {
+            Some(0) => return RangeRequest::Unsatisfiable,
+            Some(suffix) => (len.saturating_sub(suffix), len.saturating_sub(1)),
+            None => return RangeRequest::Ignored,
+        },
+        // bytes=500-: from offset 500 to the end.
+        (false, true) => match parse(start) {
+            Some(start) => (start, len.saturating_sub(1)),
+            None => return RangeRequest::Ignored,
+        },
+        (false, false) => match (parse(start), parse(end)) {
+            (Some(start), Some(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+            _ => return RangeRequest::Ignored,
+        },
+        (true, true) => return RangeRequest::Ignored,
+    };
+    if len == 0 || start >= len {
+        RangeRequest::Unsatisfiable
+    } else {
+        RangeRequest::Satisfiable(start, end)
+    }
+}
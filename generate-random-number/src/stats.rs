@@ -0,0 +1,303 @@
+//! Goodness-of-fit and independence tests used by the RNG self-test.
+//!
+//! The special functions follow the classic Numerical Recipes formulations;
+//! their accuracy is far better than what is needed to compare a p-value
+//! against a significance level.
+
+use std::f64::consts::{PI, SQRT_2};
+
+/// ln Γ(x) for x > 0 (Lanczos approximation, g = 7).
+pub fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // Reflection formula.
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let sum = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |acc, (i, c)| {
+            acc + c / (x + i as f64 + 1.0)
+        });
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// Complementary error function, fractional error below 1.2e-7.
+pub fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let r = t
+        * (-z * z - 1.265_512_23
+            + t * (1.000_023_68
+                + t * (0.374_091_96
+                    + t * (0.096_784_18
+                        + t * (-0.186_288_06
+                            + t * (0.278_868_07
+                                + t * (-1.135_203_98
+                                    + t * (1.488_515_87
+                                        + t * (-0.822_152_23 + t * 0.170_872_77)))))))))
+            .exp();
+    if x >= 0.0 {
+        r
+    } else {
+        2.0 - r
+    }
+}
+
+/// Standard normal CDF.
+pub fn normal_cdf(z: f64) -> f64 {
+    0.5 * erfc(-z / SQRT_2)
+}
+
+/// Two-sided p-value of a standard normal statistic.
+pub fn normal_two_sided(z: f64) -> f64 {
+    erfc(z.abs() / SQRT_2)
+}
+
+/// Regularized upper incomplete gamma function Q(a, x).
+pub fn gamma_q(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let ln_prefix = -x + a * x.ln() - ln_gamma(a);
+    if x < a + 1.0 {
+        // Series for P(a, x).
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        let mut n = a;
+        for _ in 0..1000 {
+            n += 1.0;
+            term *= x / n;
+            sum += term;
+            if term.abs() < sum.abs() * 1e-15 {
+                break;
+            }
+        }
+        1.0 - sum * ln_prefix.exp()
+    } else {
+        // Continued fraction for Q(a, x), modified Lentz.
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..1000 {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < 1e-15 {
+                break;
+            }
+        }
+        ln_prefix.exp() * h
+    }
+}
+
+/// Survival function of the chi-square distribution.
+pub fn chi_square_sf(statistic: f64, df: f64) -> f64 {
+    gamma_q(df / 2.0, statistic / 2.0)
+}
+
+/// Survival function of the Kolmogorov distribution, Q_KS(λ).
+pub fn kolmogorov_sf(lambda: f64) -> f64 {
+    if lambda < 0.2 {
+        return 1.0;
+    }
+    let mut sum = 0.0;
+    let mut sign = 1.0;
+    for j in 1..=100 {
+        let j = j as f64;
+        let term = sign * 2.0 * (-2.0 * j * j * lambda * lambda).exp();
+        sum += term;
+        if term.abs() < 1e-12 {
+            break;
+        }
+        sign = -sign;
+    }
+    sum.clamp(0.0, 1.0)
+}
+
+/// Outcome of one statistical test.
+#[derive(Debug, Clone, Copy)]
+pub struct TestResult {
+    pub statistic: f64,
+    pub p_value: f64,
+}
+
+/// Pearson's chi-square test. Adjacent bins are merged until every expected
+/// count is at least 5. Returns `None` if fewer than two bins remain.
+pub fn chi_square(observed: &[u64], probabilities: &[f64]) -> Option<TestResult> {
+    let total: u64 = observed.iter().sum();
+    let mut bins: Vec<(f64, f64)> = Vec::new();
+    let (mut obs, mut exp) = (0.0, 0.0);
+    for (&o, &p) in observed.iter().zip(probabilities) {
+        obs += o as f64;
+        exp += p * total as f64;
+        if exp >= 5.0 {
+            bins.push((obs, exp));
+            (obs, exp) = (0.0, 0.0);
+        }
+    }
+    if exp > 0.0 || obs > 0.0 {
+        match bins.last_mut() {
+            Some(last) => {
+                last.0 += obs;
+                last.1 += exp;
+            }
+            None => bins.push((obs, exp)),
+        }
+    }
+    if bins.len() < 2 {
+        return None;
+    }
+    let statistic: f64 = bins.iter().map(|(o, e)| (o - e) * (o - e) / e).sum();
+    Some(TestResult {
+        statistic,
+        p_value: chi_square_sf(statistic, (bins.len() - 1) as f64),
+    })
+}
+
+/// One-sample Kolmogorov–Smirnov test on values already mapped through the
+/// hypothesised CDF (so they should be uniform on [0, 1]).
+pub fn kolmogorov_smirnov(mut u: Vec<f64>) -> Option<TestResult> {
+    if u.is_empty() {
+        return None;
+    }
+    u.sort_by(f64::total_cmp);
+    let n = u.len() as f64;
+    let d = u
+        .iter()
+        .enumerate()
+        .map(|(i, &x)| ((i + 1) as f64 / n - x).max(x - i as f64 / n))
+        .fold(0.0, f64::max);
+    let sqrt_n = n.sqrt();
+    Some(TestResult {
+        statistic: d,
+        p_value: kolmogorov_sf((sqrt_n + 0.12 + 0.11 / sqrt_n) * d),
+    })
+}
+
+/// Wald–Wolfowitz runs test on a two-symbol sequence.
+pub fn runs(symbols: &[bool]) -> Option<TestResult> {
+    let n1 = symbols.iter().filter(|&&s| s).count() as f64;
+    let n2 = symbols.len() as f64 - n1;
+    if n1 == 0.0 || n2 == 0.0 {
+        return None;
+    }
+    let runs = 1 + symbols.windows(2).filter(|w| w[0] != w[1]).count();
+    let n = n1 + n2;
+    let mean = 2.0 * n1 * n2 / n + 1.0;
+    let variance = 2.0 * n1 * n2 * (2.0 * n1 * n2 - n) / (n * n * (n - 1.0));
+    if variance <= 0.0 {
+        return None;
+    }
+    let z = (runs as f64 - mean) / variance.sqrt();
+    Some(TestResult {
+        statistic: z,
+        p_value: normal_two_sided(z),
+    })
+}
+
+/// Lag-1 serial correlation, tested against zero with the large-sample
+/// approximation r ~ N(-1/n, 1/n).
+pub fn serial_correlation(values: &[f64]) -> Option<TestResult> {
+    let n = values.len() as f64;
+    if values.len() < 3 {
+        return None;
+    }
+    let mean = values.iter().sum::<f64>() / n;
+    let denominator: f64 = values.iter().map(|x| (x - mean) * (x - mean)).sum();
+    if denominator == 0.0 {
+        return None;
+    }
+    let numerator: f64 = values
+        .windows(2)
+        .map(|w| (w[0] - mean) * (w[1] - mean))
+        .sum();
+    let r = numerator / denominator;
+    let z = (r + 1.0 / n) * n.sqrt();
+    Some(TestResult {
+        statistic: r,
+        p_value: normal_two_sided(z),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance * expected.abs().max(1.0),
+            "{actual} is not within {tolerance} of {expected}"
+        );
+    }
+
+    #[test]
+    fn ln_gamma_matches_factorials() {
+        assert_close(ln_gamma(1.0), 0.0, 1e-12);
+        assert_close(ln_gamma(2.0), 0.0, 1e-12);
+        assert_close(ln_gamma(5.0), 24f64.ln(), 1e-12);
+        assert_close(ln_gamma(10.0), 362_880f64.ln(), 1e-12);
+        assert_close(ln_gamma(0.5), PI.sqrt().ln(), 1e-12);
+        // Reflection branch.
+        assert_close(ln_gamma(0.1), 2.252_712_651_734_206, 1e-12);
+    }
+
+    #[test]
+    fn gamma_q_matches_closed_forms() {
+        for x in [0.1, 0.5, 1.5, 3.0, 10.0] {
+            // Q(1, x) = e^-x, through both the series and the continued fraction.
+            assert_close(gamma_q(1.0, x), (-x).exp(), 1e-12);
+            // Q(1/2, x) = erfc(√x), limited by the accuracy of `erfc`.
+            assert_close(gamma_q(0.5, x), erfc(x.sqrt()), 1.2e-7);
+        }
+        assert_eq!(gamma_q(3.0, 0.0), 1.0);
+        // Upper 5% points of the chi-square distribution.
+        assert_close(chi_square_sf(3.841_458_820_694_124, 1.0), 0.05, 1e-9);
+        assert_close(chi_square_sf(18.307_038_053_275_146, 10.0), 0.05, 1e-9);
+    }
+
+    #[test]
+    fn erfc_matches_known_values() {
+        assert_close(erfc(0.0), 1.0, 1.2e-7);
+        assert_close(erfc(0.5), 0.479_500_122_186_953_5, 1.2e-7);
+        assert_close(erfc(1.0), 0.157_299_207_050_285_1, 1.2e-7);
+        assert_close(erfc(-1.0), 1.842_700_792_949_715, 1.2e-7);
+        assert_close(erfc(2.0), 0.004_677_734_981_047_266, 1.2e-7);
+        assert_close(normal_cdf(1.959_963_984_540_054), 0.975, 1e-7);
+        assert_close(normal_two_sided(-1.959_963_984_540_054), 0.05, 1e-7);
+    }
+
+    #[test]
+    fn kolmogorov_sf_matches_known_values() {
+        assert_eq!(kolmogorov_sf(0.1), 1.0);
+        assert_close(kolmogorov_sf(0.5), 0.963_945_243_664_637_3, 1e-9);
+        assert_close(kolmogorov_sf(1.0), 0.269_999_671_677_354_6, 1e-9);
+        // The usual 5% critical value.
+        assert_close(kolmogorov_sf(1.358_098_639_322_55), 0.05, 1e-9);
+        assert!(kolmogorov_sf(5.0) < 1e-20);
+    }
+}
@@ -0,0 +1,320 @@
+use axum::{
+    extract::{rejection::QueryRejection, Query},
+    http::header,
+    response::IntoResponse,
+    Json,
+};
+use clap::ValueEnum;
+use data_encoding::{BASE32_NOPAD, BASE64, BASE64URL_NOPAD, HEXLOWER};
+use rand::{rngs::OsRng, Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+use crate::generator::ParamError;
+
+pub const MAX_LENGTH: usize = 1024;
+
+const LOWER: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPER: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGIT: &str = "0123456789";
+const SYMBOL: &str = "!#$%&()*+,-./:;<=>?@[]^_{|}~";
+/// Characters that are easy to confuse when read or typed by a person.
+const AMBIGUOUS: &str = "Il1|O0o`'\".,;:";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenKind {
+    #[default]
+    Password,
+    Token,
+    Hex,
+    Base64,
+    #[value(name = "base64url")]
+    Base64Url,
+    Base32,
+    Uuid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharClass {
+    Lower,
+    Upper,
+    Digit,
+    Symbol,
+}
+
+impl CharClass {
+    fn parse(name: &str, parameter: &'static str) -> Result<Self, ParamError> {
+        match name.trim() {
+            "lower" => Ok(CharClass::Lower),
+            "upper" => Ok(CharClass::Upper),
+            "digit" => Ok(CharClass::Digit),
+            "symbol" => Ok(CharClass::Symbol),
+            other => Err(ParamError::new(
+                parameter,
+                format!(
+                    "unknown character class `{other}`, expected lower, upper, digit or symbol"
+                ),
+            )),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            CharClass::Lower => "lower",
+            CharClass::Upper => "upper",
+            CharClass::Digit => "digit",
+            CharClass::Symbol => "symbol",
+        }
+    }
+
+    fn chars(self) -> &'static str {
+        match self {
+            CharClass::Lower => LOWER,
+            CharClass::Upper => UPPER,
+            CharClass::Digit => DIGIT,
+            CharClass::Symbol => SYMBOL,
+        }
+    }
+}
+
+/// Raw token options from a query string or the command line.
+/// `charset` and `require` are comma-separated class names; `length` counts
+/// characters for passwords and tokens and random bytes for the encoded kinds.
+#[derive(Debug, Default, Deserialize)]
+pub struct TokenParameters {
+    #[serde(default)]
+    pub kind: TokenKind,
+    pub length: Option<usize>,
+    pub charset: Option<String>,
+    pub require: Option<String>,
+    #[serde(default)]
+    pub exclude_ambiguous: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub value: String,
+    pub length: usize,
+    /// Estimated bits of entropy, assuming the generator is uniform.
+    pub entropy_bits: f64,
+}
+
+/// A validated token recipe. Everything it produces comes from the operating
+/// system's CSPRNG; seeds are deliberately not supported.
+#[derive(Debug)]
+pub enum TokenSpec {
+    Chars {
+        kind: TokenKind,
+        length: usize,
+        alphabet: Vec<char>,
+        /// One filtered character set per class that must appear at least once.
+        required: Vec<Vec<char>>,
+    },
+    Bytes {
+        kind: TokenKind,
+        bytes: usize,
+    },
+    Uuid,
+}
+
+impl TokenSpec {
+    pub fn new(params: &TokenParameters) -> Result<Self, ParamError> {
+        match params.kind {
+            TokenKind::Password | TokenKind::Token => Self::chars(params),
+            kind => {
+                for (name, given) in [
+                    ("charset", params.charset.is_some()),
+                    ("require", params.require.is_some()),
+                    ("exclude_ambiguous", params.exclude_ambiguous),
+                ] {
+                    if given {
+                        return Err(ParamError::new(
+                            name,
+                            "only applies to password and token kinds",
+                        ));
+                    }
+                }
+                if kind == TokenKind::Uuid {
+                    if params.length.is_some() {
+                        return Err(ParamError::new("length", "does not apply to uuid"));
+                    }
+                    return Ok(TokenSpec::Uuid);
+                }
+                let bytes = check_length(params.length.unwrap_or(32))?;
+                Ok(TokenSpec::Bytes { kind, bytes })
+            }
+        }
+    }
+
+    fn chars(params: &TokenParameters) -> Result<Self, ParamError> {
+        let password = params.kind == TokenKind::Password;
+        let classes = match &params.charset {
+            Some(raw) => parse_classes(raw, "charset")?,
+            None if password => vec![
+                CharClass::Lower,
+                CharClass::Upper,
+                CharClass::Digit,
+                CharClass::Symbol,
+            ],
+            None => vec![CharClass::Lower, CharClass::Upper, CharClass::Digit],
+        };
+        let required = match &params.require {
+            Some(raw) => parse_classes(raw, "require")?,
+            None if password => classes.clone(),
+            None => Vec::new(),
+        };
+        if let Some(class) = required.iter().find(|c| !classes.contains(c)) {
+            return Err(ParamError::new(
+                "require",
+                format!("`{}` is not part of the charset", class.name()),
+            ));
+        }
+        let filter = |class: CharClass| -> Vec<char> {
+            class
+                .chars()
+                .chars()
+                .filter(|c| !params.exclude_ambiguous || !AMBIGUOUS.contains(*c))
+                .collect()
+        };
+        let alphabet: Vec<char> = classes.iter().flat_map(|&c| filter(c)).collect();
+        let required: Vec<Vec<char>> = required.into_iter().map(filter).collect();
+
+        let length = check_length(params.length.unwrap_or(if password { 16 } else { 32 }))?;
+        if length < required.len() {
+            return Err(ParamError::new(
+                "length",
+                format!(
+                    "must be at least {} to fit every required class",
+                    required.len()
+                ),
+            ));
+        }
+        Ok(TokenSpec::Chars {
+            kind: params.kind,
+            length,
+            alphabet,
+            required,
+        })
+    }
+
+    pub fn generate(&self) -> Token {
+        let mut rng = OsRng;
+        match self {
+            TokenSpec::Chars {
+                kind,
+                length,
+                alphabet,
+                required,
+            } => {
+                // Rejection sampling keeps the result uniform over all strings
+                // that satisfy the required classes, matching `entropy_bits`.
+                let value = loop {
+                    let candidate: String = (0..*length)
+                        .map(|_| alphabet[rng.gen_range(0..alphabet.len())])
+                        .collect();
+                    if required
+                        .iter()
+                        .all(|class| candidate.chars().any(|c| class.contains(&c)))
+                    {
+                        break candidate;
+                    }
+                };
+                Token {
+                    kind: *kind,
+                    value,
+                    length: *length,
+                    entropy_bits: chars_entropy(*length, alphabet.len(), required),
+                }
+            }
+            TokenSpec::Bytes { kind, bytes } => {
+                let mut buf = vec![0u8; *bytes];
+                rng.fill_bytes(&mut buf);
+                let value = match kind {
+                    TokenKind::Hex => HEXLOWER.encode(&buf),
+                    TokenKind::Base64 => BASE64.encode(&buf),
+                    TokenKind::Base64Url => BASE64URL_NOPAD.encode(&buf),
+                    _ => BASE32_NOPAD.encode(&buf),
+                };
+                Token {
+                    kind: *kind,
+                    length: value.len(),
+                    value,
+                    entropy_bits: (*bytes * 8) as f64,
+                }
+            }
+            TokenSpec::Uuid => {
+                let mut bytes = [0u8; 16];
+                rng.fill_bytes(&mut bytes);
+                let value = uuid::Builder::from_random_bytes(bytes)
+                    .into_uuid()
+                    .to_string();
+                Token {
+                    kind: TokenKind::Uuid,
+                    length: value.len(),
+                    value,
+                    // 6 of the 128 bits are fixed version and variant bits.
+                    entropy_bits: 122.0,
+                }
+            }
+        }
+    }
+}
+
+fn parse_classes(raw: &str, parameter: &'static str) -> Result<Vec<CharClass>, ParamError> {
+    let mut classes = Vec::new();
+    for name in raw.split(',').filter(|s| !s.trim().is_empty()) {
+        let class = CharClass::parse(name, parameter)?;
+        if !classes.contains(&class) {
+            classes.push(class);
+        }
+    }
+    if classes.is_empty() && parameter == "charset" {
+        return Err(ParamError::new(parameter, "must name at least one class"));
+    }
+    Ok(classes)
+}
+
+fn check_length(length: usize) -> Result<usize, ParamError> {
+    if (1..=MAX_LENGTH).contains(&length) {
+        Ok(length)
+    } else {
+        Err(ParamError::new(
+            "length",
+            format!("must be between 1 and {MAX_LENGTH}"),
+        ))
+    }
+}
+
+/// log2 of the number of `length`-character strings over an alphabet of
+/// `size` that contain every required class, by inclusion–exclusion.
+fn chars_entropy(length: usize, size: usize, required: &[Vec<char>]) -> f64 {
+    let n = size as f64;
+    let mut fraction = 0.0;
+    for subset in 0u32..(1 << required.len()) {
+        let excluded: usize = required
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| subset & (1 << i) != 0)
+            .map(|(_, class)| class.len())
+            .sum();
+        let term = (1.0 - excluded as f64 / n).powi(length as i32);
+        if subset.count_ones() % 2 == 0 {
+            fraction += term;
+        } else {
+            fraction -= term;
+        }
+    }
+    let bits = length as f64 * n.log2() + fraction.log2();
+    (bits * 100.0).round() / 100.0
+}
+
+/// `GET /token?kind=password&length=20&exclude_ambiguous=true`
+pub async fn token_handler(
+    params: Result<Query<TokenParameters>, QueryRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let Query(params) = params?;
+    let token = TokenSpec::new(&params)?.generate();
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(token)))
+}
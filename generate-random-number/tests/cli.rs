@@ -0,0 +1,130 @@
+use std::process::{Command, Output, Stdio};
+
+use serde_json::Value;
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_generate-random-number"))
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+fn stderr(output: &Output) -> &str {
+    std::str::from_utf8(&output.stderr).unwrap()
+}
+
+#[test]
+fn seeded_ints_are_reproducible() {
+    let args = [
+        "int", "--start", "-5", "--end", "5", "--count", "20", "--seed", "42",
+    ];
+    let first = run(&args);
+    assert!(first.status.success(), "{}", stderr(&first));
+    assert_eq!(stderr(&first), "seed: 42 stream: 0 index: 0\n");
+    let values: Vec<i64> = stdout(&first)
+        .lines()
+        .map(|line| line.parse().unwrap())
+        .collect();
+    assert_eq!(values.len(), 20);
+    assert!(values.iter().all(|v| (-5..5).contains(v)));
+    assert_eq!(stdout(&run(&args)), stdout(&first));
+
+    // An offset continues the same sequence.
+    let rest = run(&[
+        "int", "--start", "-5", "--end", "5", "--count", "15", "--seed", "42", "--offset", "5",
+    ]);
+    let first_lines: Vec<&str> = stdout(&first).lines().skip(5).collect();
+    assert_eq!(stdout(&rest).lines().collect::<Vec<_>>(), first_lines);
+}
+
+#[test]
+fn json_and_csv_output() {
+    let json = run(&[
+        "float", "--start", "0", "--end", "1", "--count", "3", "--seed", "1", "--format", "json",
+    ]);
+    let records: Vec<Value> = stdout(&json)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[2]["index"], 2);
+
+    let csv = run(&[
+        "token", "--kind", "hex", "--length", "4", "--count", "2", "--format", "csv",
+    ]);
+    let lines: Vec<&str> = stdout(&csv).lines().collect();
+    assert_eq!(lines[0], "value,entropy_bits");
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].ends_with(",32"), "{}", lines[1]);
+}
+
+#[test]
+fn list_commands_take_positional_items() {
+    let shuffled = run(&["shuffle", "a", "b", "c", "d", "--seed", "3"]);
+    let mut items: Vec<&str> = stdout(&shuffled).lines().collect();
+    items.sort_unstable();
+    assert_eq!(items, ["a", "b", "c", "d"]);
+
+    let sample = run(&["sample", "--k", "2", "a", "b", "c", "--seed", "3"]);
+    assert_eq!(stdout(&sample).lines().count(), 2);
+
+    let empty = run(&["pick"]);
+    assert_eq!(empty.status.code(), Some(2));
+    assert_eq!(stderr(&empty), "error: <ITEMS>: must not be empty\n");
+}
+
+#[test]
+fn parameter_errors_name_the_argument() {
+    let range = run(&["int", "--start", "5", "--end", "1"]);
+    assert_eq!(range.status.code(), Some(2));
+    assert!(
+        stderr(&range).starts_with("error: --end: "),
+        "{}",
+        stderr(&range)
+    );
+
+    let token = run(&["token", "--kind", "hex", "--exclude-ambiguous"]);
+    assert_eq!(token.status.code(), Some(2));
+    assert!(
+        stderr(&token).starts_with("error: --exclude-ambiguous: "),
+        "{}",
+        stderr(&token)
+    );
+
+    let dice = run(&["dice", "2d6+"]);
+    assert_eq!(dice.status.code(), Some(2));
+    assert_eq!(
+        stderr(&dice),
+        "2d6+\n    ^\nerror: <EXPR>: column 5: expected a number or dice\n"
+    );
+}
+
+#[test]
+fn selftest_and_verify_exit_codes() {
+    let passed = run(&[
+        "selftest",
+        "--seed",
+        "1",
+        "--samples",
+        "2000",
+        "float",
+        "--start",
+        "0",
+        "--end",
+        "1",
+    ]);
+    assert!(passed.status.success(), "{}", stdout(&passed));
+    assert_eq!(stdout(&passed).lines().last(), Some("PASS"));
+
+    let invalid = run(&["selftest", "--samples", "1", "bool"]);
+    assert_eq!(invalid.status.code(), Some(2));
+    assert!(stderr(&invalid).starts_with("error: --samples: "));
+
+    let missing = run(&["verify", "/nonexistent/ledger.json"]);
+    assert_eq!(missing.status.code(), Some(2));
+}
@@ -0,0 +1,1044 @@
+use axum::http::StatusCode;
+use std::time::Duration;
+
+use generate_random_number::{
+    fairness::FairStore,
+    ratelimit::{RateLimitConfig, RateLimiter},
+    state::AppState,
+    static_files::StaticFiles,
+};
+use serde_json::{json, Value};
+use server_core::testing::TestClient;
+
+fn client_with(config: RateLimitConfig) -> TestClient {
+    let files = StaticFiles::new(env!("CARGO_MANIFEST_DIR"));
+    let limiter = RateLimiter::new(config).unwrap();
+    TestClient::new(generate_random_number::app(AppState::new(files, limiter)))
+}
+
+fn client() -> TestClient {
+    client_with(RateLimitConfig::default())
+}
+
+#[tokio::test]
+async fn seeded_int_is_reproducible() {
+    let client = client();
+    let mut values = Vec::new();
+    for offset in 0..3 {
+        let response = client
+            .get(&format!(
+                "/random/int?start=0&end=100&seed=42&offset={offset}"
+            ))
+            .header("accept", "application/json")
+            .send()
+            .await;
+        response
+            .assert_status(StatusCode::OK)
+            .assert_header("x-random-seed", "42")
+            .assert_header("x-random-index", &offset.to_string());
+        let body: Value = response.json();
+        assert_eq!(body["seed"], 42);
+        values.push(body["value"].as_i64().unwrap());
+    }
+    assert_eq!(values, [68, 95, 42]);
+}
+
+#[tokio::test]
+async fn batch_matches_single_draws() {
+    let response = client()
+        .get("/random/int?start=0&end=100&seed=42&count=3&format=ndjson")
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    let values: Vec<Value> = response
+        .text()
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap()["value"].clone())
+        .collect();
+    assert_eq!(values, [json!(68), json!(95), json!(42)]);
+}
+
+#[tokio::test]
+async fn html_is_rendered_from_the_template() {
+    client()
+        .get("/random/int?start=0&end=100&seed=42")
+        .header("accept", "text/html")
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_header("vary", "accept")
+        .assert_html_snapshot("random_int_seed_42");
+}
+
+#[tokio::test]
+async fn format_parameter_wins_over_accept() {
+    let response = client()
+        .get("/random/bool?seed=1&format=text")
+        .header("accept", "application/json")
+        .send()
+        .await;
+    response.assert_status(StatusCode::OK);
+    assert!(response
+        .header("content-type")
+        .unwrap()
+        .starts_with("text/plain"));
+    assert!(matches!(response.text().trim(), "true" | "false"));
+}
+
+#[tokio::test]
+async fn unsupported_media_type_is_not_acceptable() {
+    client()
+        .get("/random/int?start=0&end=10")
+        .header("accept", "image/png")
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_ACCEPTABLE, "not_acceptable");
+}
+
+#[tokio::test]
+async fn cbor_and_text_bodies_match_json() {
+    let client = client();
+    let uri = "/random/int?start=0&end=100&seed=42";
+    let json: Value = client
+        .get(uri)
+        .header("accept", "application/json")
+        .send()
+        .await
+        .json();
+
+    let cbor = client
+        .get(uri)
+        .header("accept", "application/cbor")
+        .send()
+        .await;
+    cbor.assert_status(StatusCode::OK)
+        .assert_header("content-type", "application/cbor")
+        .assert_header("x-random-seed", "42");
+    let decoded: Value = ciborium::from_reader(cbor.bytes().as_ref()).unwrap();
+    assert_eq!(decoded, json);
+
+    let text = client
+        .get(uri)
+        .header("accept", "application/cbor;q=0.5, text/plain")
+        .send()
+        .await;
+    text.assert_status(StatusCode::OK)
+        .assert_header("content-type", "text/plain; charset=utf-8")
+        .assert_header("x-random-index", "0");
+    assert_eq!(text.text(), format!("{}\n", json["value"]));
+}
+
+#[tokio::test]
+async fn empty_range_is_rejected() {
+    let response = client()
+        .get("/random/int?start=1&end=1")
+        .header("accept", "application/json")
+        .send()
+        .await;
+    response.assert_error(StatusCode::BAD_REQUEST, "invalid_parameter");
+    assert_eq!(response.json::<Value>()["parameter"], "end");
+}
+
+#[tokio::test]
+async fn overflowing_float_range_is_rejected() {
+    for inclusive in [false, true] {
+        let response = client()
+            .get(&format!(
+                "/random/float?start=-1e308&end=1e308&inclusive={inclusive}"
+            ))
+            .header("accept", "application/json")
+            .send()
+            .await;
+        response.assert_error(StatusCode::BAD_REQUEST, "invalid_parameter");
+        assert_eq!(response.json::<Value>()["parameter"], "end");
+    }
+}
+
+#[tokio::test]
+async fn huge_poisson_lambda_is_rejected() {
+    let client = client();
+    let response = client
+        .get("/random/dist?dist=poisson&lambda=1e30")
+        .header("accept", "application/json")
+        .send()
+        .await;
+    response.assert_error(StatusCode::BAD_REQUEST, "invalid_parameter");
+    assert_eq!(response.json::<Value>()["parameter"], "lambda");
+    client
+        .get("/random/dist?dist=poisson&lambda=1e15")
+        .header("accept", "application/json")
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+#[tokio::test]
+async fn overflowing_categorical_weights_are_rejected() {
+    let response = client()
+        .get("/random/dist?dist=categorical&weights=1e308,1e308")
+        .header("accept", "application/json")
+        .send()
+        .await;
+    response.assert_error(StatusCode::BAD_REQUEST, "invalid_parameter");
+    assert_eq!(response.json::<Value>()["parameter"], "weights");
+}
+
+#[tokio::test]
+async fn dice_errors_point_at_the_column() {
+    let response = client().get("/dice?expr=3d6%2B%2B2").send().await;
+    response.assert_error(StatusCode::BAD_REQUEST, "parse_error");
+    assert_eq!(response.json::<Value>()["column"], 5);
+}
+
+#[tokio::test]
+async fn seeded_dice_roll() {
+    let response = client().get("/dice?expr=3d6%2B2&seed=7").send().await;
+    response.assert_status(StatusCode::OK);
+    let body: Value = response.json();
+    assert_eq!(body["expr"], "3d6 + 2");
+    assert_eq!(body["terms"][0]["rolls"], json!([2, 2, 2]));
+    assert_eq!(body["total"], 8);
+}
+
+#[tokio::test]
+async fn dice_offset_is_limited_by_the_dice_thrown() {
+    let client = client();
+    client
+        .get("/dice?expr=1000d6&seed=1&offset=10")
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    let response = client
+        .get("/dice?expr=1000d6&seed=1&offset=10001")
+        .send()
+        .await;
+    response.assert_error(StatusCode::BAD_REQUEST, "invalid_parameter");
+    assert_eq!(response.json::<Value>()["parameter"], "offset");
+}
+
+#[tokio::test]
+async fn seeded_shuffle_is_reproducible() {
+    let client = client();
+    let request = json!({ "items": [1, 2, 3, 4, 5], "seed": 42 });
+    let first: Value = client.post("/shuffle").json(&request).send().await.json();
+    let second: Value = client.post("/shuffle").json(&request).send().await.json();
+    assert_eq!(first["items"], json!([4, 3, 2, 1, 5]));
+    assert_eq!(first, second);
+}
+
+#[tokio::test]
+async fn list_offset_is_limited_by_the_list_size() {
+    let client = client();
+    let items: Vec<u32> = (0..1000).collect();
+    for path in ["/shuffle", "/sample"] {
+        let request = json!({ "items": items, "k": 3, "seed": 1, "offset": 10001 });
+        let response = client.post(path).json(&request).send().await;
+        response.assert_error(StatusCode::BAD_REQUEST, "invalid_parameter");
+        assert_eq!(response.json::<Value>()["parameter"], "offset");
+    }
+    let request = json!({ "items": items, "count": 1000, "seed": 1, "offset": 10001 });
+    client
+        .post("/pick")
+        .json(&request)
+        .send()
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "invalid_parameter");
+    let request = json!({ "items": items, "seed": 1, "offset": 10 });
+    client
+        .post("/shuffle")
+        .json(&request)
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+#[tokio::test]
+async fn revealed_ledger_verifies_after_a_json_round_trip() {
+    let client = client();
+    let created = client
+        .post("/fair")
+        .json(&json!({ "client_seed": "player-1" }))
+        .send()
+        .await;
+    created.assert_status(StatusCode::CREATED);
+    let id = created.json::<Value>()["id"].as_str().unwrap().to_string();
+
+    let draw = format!("/fair/{id}/draw");
+    for _ in 0..40 {
+        client
+            .post(&draw)
+            .json(&json!({ "kind": "float", "start": 0, "end": 1 }))
+            .send()
+            .await
+            .assert_status(StatusCode::CREATED);
+    }
+    let pick = json!({
+        "kind": "pick",
+        "items": [0.1, 1e-7, { "a": 1.5 }, "x"],
+        "weights": [0.3, 0.3, 0.2, 0.2],
+    });
+    for _ in 0..10 {
+        client
+            .post(&draw)
+            .json(&pick)
+            .send()
+            .await
+            .assert_status(StatusCode::CREATED);
+    }
+
+    let log: Value = client
+        .post(&format!("/fair/{id}/reveal"))
+        .send()
+        .await
+        .json();
+    let verification: Value = client.post("/fair/verify").json(&log).send().await.json();
+    assert_eq!(verification["issues"], json!([]));
+    assert_eq!(verification["valid"], true);
+    assert_eq!(verification["entries_checked"], 50);
+}
+
+#[tokio::test]
+async fn ledger_body_is_optional_but_checked() {
+    let client = client();
+    let created = client.post("/fair").send().await;
+    created.assert_status(StatusCode::CREATED);
+    assert_eq!(created.json::<Value>()["client_seed"], "");
+
+    client
+        .post("/fair")
+        .json(&json!({ "client_seed": 5 }))
+        .send()
+        .await
+        .assert_error(StatusCode::UNPROCESSABLE_ENTITY, "invalid_body");
+    client
+        .post("/fair")
+        .header("content-type", "application/json")
+        .body("{")
+        .send()
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "invalid_body");
+    client
+        .post("/fair")
+        .body("client_seed=5")
+        .send()
+        .await
+        .assert_error(StatusCode::UNSUPPORTED_MEDIA_TYPE, "invalid_body");
+}
+
+#[tokio::test]
+async fn tampered_ledger_fails_verification() {
+    let client = client();
+    let id = client.post("/fair").send().await.json::<Value>()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    client
+        .post(&format!("/fair/{id}/draw"))
+        .json(&json!({ "kind": "int", "start": 0, "end": 100 }))
+        .send()
+        .await
+        .assert_status(StatusCode::CREATED);
+    let mut log: Value = client
+        .post(&format!("/fair/{id}/reveal"))
+        .send()
+        .await
+        .json();
+    let result = log["entries"][0]["result"].as_i64().unwrap();
+    log["entries"][0]["result"] = json!((result + 1) % 100);
+    let verification: Value = client.post("/fair/verify").json(&log).send().await.json();
+    assert_eq!(verification["valid"], false);
+    assert_eq!(verification["issues"][0]["index"], 0);
+}
+
+#[tokio::test]
+async fn expired_and_deleted_ledgers_free_their_slot() {
+    let files = StaticFiles::new(env!("CARGO_MANIFEST_DIR"));
+    let limiter = RateLimiter::new(RateLimitConfig::default()).unwrap();
+    let mut state = AppState::new(files, limiter);
+    state.fair = FairStore::new(Duration::from_millis(50), 1);
+    let client = TestClient::new(generate_random_number::app(state));
+
+    let created = client.post("/fair").send().await;
+    created.assert_status(StatusCode::CREATED);
+    client
+        .post("/fair")
+        .send()
+        .await
+        .assert_error(StatusCode::SERVICE_UNAVAILABLE, "too_many_ledgers");
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let id = created.json::<Value>()["id"].as_str().unwrap().to_string();
+    client
+        .get(&format!("/fair/{id}"))
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+    let created = client.post("/fair").send().await;
+    created.assert_status(StatusCode::CREATED);
+
+    let id = created.json::<Value>()["id"].as_str().unwrap().to_string();
+    client
+        .delete(&format!("/fair/{id}"))
+        .send()
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    client
+        .post("/fair")
+        .send()
+        .await
+        .assert_status(StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn overflowing_pick_weights_are_rejected() {
+    let client = client();
+    let request = json!({ "items": ["a", "b"], "weights": [1e308, 1e308] });
+    let response = client.post("/pick").json(&request).send().await;
+    response.assert_error(StatusCode::BAD_REQUEST, "invalid_parameter");
+    assert_eq!(response.json::<Value>()["parameter"], "weights");
+
+    // Fairness draws and their verification go through the same picker.
+    let id = client.post("/fair").send().await.json::<Value>()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let spec = json!({ "kind": "pick", "items": ["a", "b"], "weights": [1e308, 1e308] });
+    client
+        .post(&format!("/fair/{id}/draw"))
+        .json(&spec)
+        .send()
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "invalid_parameter");
+    let mut log: Value = client
+        .post(&format!("/fair/{id}/reveal"))
+        .send()
+        .await
+        .json();
+    log["entries"] = json!([{
+        "index": 0,
+        "spec": spec,
+        "result": "a",
+        "prev_hash": log["commitment"],
+        "hash": "",
+    }]);
+    let verification: Value = client.post("/fair/verify").json(&log).send().await.json();
+    assert_eq!(verification["valid"], false);
+}
+
+#[tokio::test]
+async fn rate_limit_headers_and_429() {
+    let mut config = RateLimitConfig::default();
+    config.set_route("/dice=2:0.001".parse().unwrap());
+    let client = client_with(config);
+
+    let first = client.get("/dice?expr=1d6").send().await;
+    first
+        .assert_status(StatusCode::OK)
+        .assert_header("ratelimit-limit", "2")
+        .assert_header("ratelimit-remaining", "1");
+    client.get("/dice?expr=1d6").send().await;
+    let limited = client.get("/dice?expr=1d6").send().await;
+    limited.assert_error(StatusCode::TOO_MANY_REQUESTS, "rate_limited");
+    assert!(limited.header("retry-after").is_some());
+
+    // Buckets are per client.
+    client
+        .get("/dice?expr=1d6")
+        .peer("10.0.0.2:4000".parse().unwrap())
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+#[tokio::test]
+async fn unknown_api_keys_share_the_ip_bucket() {
+    let mut config = RateLimitConfig::default();
+    config.set_route("/dice=2:0.001".parse().unwrap());
+    config.api_keys = vec!["known".into()];
+    let client = client_with(config);
+
+    // A fresh made-up key per request buys nothing.
+    for key in ["a", "b"] {
+        client
+            .get("/dice?expr=1d6")
+            .header("x-api-key", key)
+            .send()
+            .await
+            .assert_status(StatusCode::OK);
+    }
+    client
+        .get("/dice?expr=1d6")
+        .header("x-api-key", "c")
+        .send()
+        .await
+        .assert_error(StatusCode::TOO_MANY_REQUESTS, "rate_limited");
+
+    // A configured key has its own bucket.
+    client
+        .get("/dice?expr=1d6")
+        .header("x-api-key", "known")
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+#[tokio::test]
+async fn clients_beyond_the_cap_share_a_bucket() {
+    let mut config = RateLimitConfig::default();
+    config.set_route("/dice=1:0.001".parse().unwrap());
+    config.max_clients = 1;
+    let client = client_with(config);
+
+    let mut statuses = Vec::new();
+    for peer in ["10.0.0.1:1", "10.0.0.2:1", "10.0.0.3:1"] {
+        let response = client
+            .get("/dice?expr=1d6")
+            .peer(peer.parse().unwrap())
+            .send()
+            .await;
+        statuses.push(response.status());
+    }
+    assert_eq!(
+        statuses,
+        [
+            StatusCode::OK,
+            StatusCode::OK,
+            StatusCode::TOO_MANY_REQUESTS
+        ]
+    );
+}
+
+#[tokio::test]
+async fn static_page_and_traversal() {
+    let client = client();
+    let page = client.get("/").send().await;
+    page.assert_status(StatusCode::OK);
+    assert!(page.header("etag").is_some());
+    assert!(page
+        .header("content-type")
+        .unwrap()
+        .starts_with("text/html"));
+
+    let status = client
+        .get("/sina_files/..%2FCargo.toml")
+        .send()
+        .await
+        .status();
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn embedded_page_is_served_without_a_static_root() {
+    let files = StaticFiles::embedded();
+    assert_eq!(files.check().await, Ok(()));
+    let missing = StaticFiles::new(concat!(env!("CARGO_MANIFEST_DIR"), "/src"));
+    assert!(missing.check().await.is_err());
+
+    let limiter = RateLimiter::new(RateLimitConfig::default()).unwrap();
+    let client = TestClient::new(generate_random_number::app(AppState::new(files, limiter)));
+    let page = client.get("/").send().await;
+    page.assert_status(StatusCode::OK);
+    assert_eq!(page.bytes(), include_bytes!("../sina.html").as_slice());
+    let etag = page.header("etag").unwrap().to_string();
+    client
+        .get("/sina.html")
+        .header("if-none-match", &etag)
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_MODIFIED);
+    client
+        .get("/sina_files/index_style.css")
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+}
+
+/// `sina_files/fix.js.下载`, percent-encoded.
+const ASSET: &str = "/sina_files/fix.js.%E4%B8%8B%E8%BD%BD";
+
+/// A static root holding a stub page and a saved-page asset.
+fn static_client(test: &str) -> TestClient {
+    let root = std::env::temp_dir().join(format!(
+        "generate-random-number-{test}-{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(root.join("sina_files")).unwrap();
+    std::fs::write(root.join("sina.html"), "<html></html>").unwrap();
+    std::fs::write(root.join("sina_files/fix.js.下载"), "0123456789").unwrap();
+    let limiter = RateLimiter::new(RateLimitConfig::default()).unwrap();
+    let state = AppState::new(StaticFiles::new(root), limiter);
+    TestClient::new(generate_random_number::app(state))
+}
+
+#[test]
+fn saved_page_suffix_is_ignored_for_content_type() {
+    use generate_random_number::static_files::content_type;
+    use std::path::Path;
+
+    assert_eq!(
+        content_type(Path::new("fix.js.下载")),
+        content_type(Path::new("fix.js"))
+    );
+    assert!(content_type(Path::new("fix.js.下载")).ends_with("javascript; charset=utf-8"));
+    assert_eq!(
+        content_type(Path::new("index_style.css.下载")),
+        "text/css; charset=utf-8"
+    );
+    assert_eq!(content_type(Path::new("logo.png")), "image/png");
+}
+
+#[tokio::test]
+async fn static_files_honour_conditional_requests() {
+    let client = static_client("conditional");
+    let asset = client.get(ASSET).send().await;
+    asset
+        .assert_status(StatusCode::OK)
+        .assert_header("accept-ranges", "bytes")
+        .assert_header("x-content-type-options", "nosniff");
+    assert!(asset
+        .header("content-type")
+        .unwrap()
+        .ends_with("javascript; charset=utf-8"));
+    assert_eq!(asset.text(), "0123456789");
+    let etag = asset.header("etag").unwrap().to_string();
+    let last_modified = asset.header("last-modified").unwrap().to_string();
+
+    let revalidated = client
+        .get(ASSET)
+        .header("if-none-match", &format!("\"other\", W/{etag}"))
+        .send()
+        .await;
+    revalidated
+        .assert_status(StatusCode::NOT_MODIFIED)
+        .assert_header("etag", &etag);
+    assert!(revalidated.bytes().is_empty());
+    client
+        .get(ASSET)
+        .header("if-modified-since", &last_modified)
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_MODIFIED);
+    // A stale If-None-Match wins over a matching If-Modified-Since.
+    client
+        .get(ASSET)
+        .header("if-none-match", "\"other\"")
+        .header("if-modified-since", &last_modified)
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+}
+
+#[tokio::test]
+async fn static_files_serve_byte_ranges() {
+    let client = static_client("ranges");
+    let range = |value: &'static str| client.get(ASSET).header("range", value).send();
+
+    let partial = range("bytes=2-5").await;
+    partial
+        .assert_status(StatusCode::PARTIAL_CONTENT)
+        .assert_header("content-range", "bytes 2-5/10")
+        .assert_header("content-length", "4");
+    assert_eq!(partial.text(), "2345");
+
+    let suffix = range("bytes=-3").await;
+    suffix
+        .assert_status(StatusCode::PARTIAL_CONTENT)
+        .assert_header("content-range", "bytes 7-9/10");
+    assert_eq!(suffix.text(), "789");
+
+    let open_ended = range("bytes=8-").await;
+    open_ended.assert_header("content-range", "bytes 8-9/10");
+    assert_eq!(open_ended.text(), "89");
+
+    range("bytes=10-")
+        .await
+        .assert_status(StatusCode::RANGE_NOT_SATISFIABLE)
+        .assert_header("content-range", "bytes */10");
+
+    // Multiple ranges are answered with the whole file.
+    let whole = range("bytes=0-1,4-5").await;
+    whole.assert_status(StatusCode::OK);
+    assert_eq!(whole.text(), "0123456789");
+}
+
+#[tokio::test]
+async fn static_files_check_if_range() {
+    let client = static_client("if-range");
+    let etag = client
+        .get(ASSET)
+        .send()
+        .await
+        .header("etag")
+        .unwrap()
+        .to_string();
+
+    let matching = client
+        .get(ASSET)
+        .header("range", "bytes=0-2")
+        .header("if-range", &etag)
+        .send()
+        .await;
+    matching.assert_status(StatusCode::PARTIAL_CONTENT);
+    assert_eq!(matching.text(), "012");
+
+    let stale = client
+        .get(ASSET)
+        .header("range", "bytes=0-2")
+        .header("if-range", "\"stale\"")
+        .send()
+        .await;
+    stale.assert_status(StatusCode::OK);
+    assert!(stale.header("content-range").is_none());
+    assert_eq!(stale.text(), "0123456789");
+}
+
+#[tokio::test]
+async fn selftest_reports_every_test() {
+    let client = client();
+    let report: Value = client
+        .get("/selftest/float?start=0&end=1&generator=chacha8&seed=1&samples=5000")
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .json();
+    assert_eq!(report["generator"], "chacha8");
+    assert_eq!(report["seed"], 1);
+    assert_eq!(report["samples"], 5000);
+    assert_eq!(report["passed"], true);
+    let names: Vec<&str> = report["tests"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "chi_square",
+            "kolmogorov_smirnov",
+            "runs",
+            "serial_correlation"
+        ]
+    );
+    for test in report["tests"].as_array().unwrap() {
+        let p_value = test["p_value"].as_f64().unwrap();
+        assert!((0.0..=1.0).contains(&p_value), "{test}");
+    }
+
+    // Kolmogorov–Smirnov does not apply to a discrete distribution.
+    let report: Value = client
+        .get("/selftest/int?start=1&end=7&seed=1")
+        .send()
+        .await
+        .json();
+    assert_eq!(report["tests"][0]["passed"], true);
+    assert!(report["tests"][1]["skipped"].is_string());
+
+    client
+        .get("/selftest/int?start=1&end=7&samples=1")
+        .send()
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "invalid_parameter");
+    client
+        .get("/selftest/int?start=1&end=7&alpha=1")
+        .send()
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "invalid_parameter");
+}
+
+async fn token(client: &TestClient, query: &str) -> Value {
+    client
+        .get(&format!("/token?{query}"))
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_header("cache-control", "no-store")
+        .json()
+}
+
+#[tokio::test]
+async fn tokens_use_the_requested_alphabet() {
+    let client = client();
+    let password = token(&client, "").await;
+    let value = password["value"].as_str().unwrap();
+    assert_eq!(password["kind"], "password");
+    assert_eq!(value.chars().count(), 16);
+    for class in [
+        "abcdefghijklmnopqrstuvwxyz",
+        "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+        "0123456789",
+        "!#$%&()*+,-./:;<=>?@[]^_{|}~",
+    ] {
+        assert!(value.chars().any(|c| class.contains(c)), "{value}");
+    }
+
+    let digits = token(&client, "kind=token&charset=digit&length=40").await;
+    assert!(digits["value"]
+        .as_str()
+        .unwrap()
+        .chars()
+        .all(|c| c.is_ascii_digit()));
+
+    let unambiguous = token(
+        &client,
+        "kind=password&charset=lower,upper,digit,symbol&length=1024&exclude_ambiguous=true",
+    )
+    .await;
+    let value = unambiguous["value"].as_str().unwrap();
+    assert_eq!(value.len(), 1024);
+    assert!(
+        !value.contains(|c| "Il1|O0o`'\".,;:".contains(c)),
+        "{value}"
+    );
+
+    let hex = token(&client, "kind=hex&length=16").await;
+    let value = hex["value"].as_str().unwrap();
+    assert_eq!(hex["length"], 32);
+    assert!(value.chars().all(|c| "0123456789abcdef".contains(c)));
+    let base64url = token(&client, "kind=base64url&length=32").await;
+    assert_eq!(base64url["value"].as_str().unwrap().len(), 43);
+    let uuid = token(&client, "kind=uuid").await;
+    let value = uuid["value"].as_str().unwrap();
+    assert_eq!(value.len(), 36);
+    assert_eq!(&value[14..15], "4");
+}
+
+#[tokio::test]
+async fn tokens_report_their_entropy() {
+    let client = client();
+    let digits = token(&client, "kind=token&charset=digit&length=10").await;
+    assert_eq!(digits["entropy_bits"], 33.22);
+    // 36² two-character strings, of which 26² have no digit.
+    let required = token(
+        &client,
+        "kind=password&charset=lower,digit&require=digit&length=2",
+    )
+    .await;
+    assert_eq!(required["entropy_bits"], 9.28);
+    assert_eq!(
+        token(&client, "kind=hex&length=16").await["entropy_bits"],
+        128.0
+    );
+    assert_eq!(token(&client, "kind=uuid").await["entropy_bits"], 122.0);
+}
+
+#[tokio::test]
+async fn token_parameters_are_validated() {
+    let client = client();
+    token(&client, "kind=token&length=1024").await;
+    token(&client, "kind=base32&length=1").await;
+    for (query, parameter) in [
+        ("length=0", "length"),
+        ("kind=token&length=1025", "length"),
+        ("kind=hex&length=0", "length"),
+        ("length=3", "length"),
+        ("kind=uuid&length=16", "length"),
+        ("charset=emoji", "charset"),
+        ("charset=,", "charset"),
+        ("charset=lower&require=digit", "require"),
+        ("require=nope", "require"),
+        ("kind=hex&charset=lower", "charset"),
+        ("kind=base64&exclude_ambiguous=true", "exclude_ambiguous"),
+    ] {
+        let response = client.get(&format!("/token?{query}")).send().await;
+        response.assert_error(StatusCode::BAD_REQUEST, "invalid_parameter");
+        assert_eq!(response.json::<Value>()["parameter"], parameter, "{query}");
+    }
+}
+
+#[tokio::test]
+async fn sessions_never_repeat_a_value() {
+    let client = client();
+    client
+        .post("/sessions")
+        .json(&json!({"name": "raffle", "start": 1, "end": 50, "inclusive": true}))
+        .send()
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    let mut drawn = Vec::new();
+    for count in [1, 7, 20, 22] {
+        let draw: Value = client
+            .post(&format!("/sessions/raffle/draw?count={count}"))
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .json();
+        drawn.extend(
+            draw["values"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|v| v.as_i64().unwrap()),
+        );
+        assert_eq!(draw["remaining"], 50 - drawn.len());
+    }
+    drawn.sort_unstable();
+    assert_eq!(drawn, (1..=50).collect::<Vec<_>>());
+
+    let info: Value = client.get("/sessions/raffle").send().await.json();
+    assert_eq!(info["remaining"], 0);
+    assert_eq!(info["drawn"].as_array().unwrap().len(), 50);
+}
+
+#[tokio::test]
+async fn exhausted_sessions_conflict() {
+    let client = client();
+    client
+        .post("/sessions")
+        .json(&json!({"name": "prizes", "items": ["a", "b", "c"]}))
+        .send()
+        .await
+        .assert_status(StatusCode::CREATED);
+    client
+        .post("/sessions")
+        .json(&json!({"name": "prizes", "items": ["d"]}))
+        .send()
+        .await
+        .assert_error(StatusCode::CONFLICT, "session_exists");
+
+    client
+        .post("/sessions/prizes/draw?count=2")
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    // Asking for more than is left draws nothing.
+    client
+        .post("/sessions/prizes/draw?count=2")
+        .send()
+        .await
+        .assert_error(StatusCode::CONFLICT, "exhausted");
+    let last: Value = client.post("/sessions/prizes/draw").send().await.json();
+    assert_eq!(last["remaining"], 0);
+    client
+        .post("/sessions/prizes/draw")
+        .send()
+        .await
+        .assert_error(StatusCode::CONFLICT, "exhausted");
+
+    let reset: Value = client.post("/sessions/prizes/reset").send().await.json();
+    assert_eq!(reset["remaining"], 3);
+    assert_eq!(reset["drawn"], json!([]));
+}
+
+#[tokio::test]
+async fn session_ranges_must_fit_in_u64() {
+    let client = client();
+    let response = client
+        .post("/sessions")
+        .json(&json!({
+            "name": "everything",
+            "start": i64::MIN,
+            "end": i64::MAX,
+            "inclusive": true,
+        }))
+        .send()
+        .await;
+    response.assert_error(StatusCode::BAD_REQUEST, "invalid_parameter");
+    assert_eq!(response.json::<Value>()["parameter"], "end");
+
+    // One value fewer fits, and the sparse shuffle handles it.
+    let created: Value = client
+        .post("/sessions")
+        .json(&json!({"name": "everything", "start": i64::MIN, "end": i64::MAX}))
+        .send()
+        .await
+        .assert_status(StatusCode::CREATED)
+        .json();
+    assert_eq!(created["total"], u64::MAX);
+    let draw: Value = client
+        .post("/sessions/everything/draw?count=1000")
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .json();
+    assert_eq!(draw["remaining"], u64::MAX - 1000);
+}
+
+/// The `id` and parsed `data` of each event in an SSE body.
+fn sse_events(body: &str) -> Vec<(String, Value)> {
+    body.split("\n\n")
+        .filter_map(|event| {
+            let field = |name: &str| {
+                event
+                    .lines()
+                    .find_map(|line| line.strip_prefix(name))
+                    .map(|value| value.trim_start().to_string())
+            };
+            Some((
+                field("id:")?,
+                serde_json::from_str(&field("data:")?).unwrap(),
+            ))
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn event_stream_ends_after_count() {
+    let client = client();
+    let mut expected = Vec::new();
+    for offset in 0..3 {
+        let body: Value = client
+            .get(&format!(
+                "/random/int?start=0&end=100&seed=42&offset={offset}"
+            ))
+            .header("accept", "application/json")
+            .send()
+            .await
+            .json();
+        expected.push(body["value"].clone());
+    }
+
+    let response = client
+        .get("/random/int/events?start=0&end=100&seed=42&count=3&interval_ms=10")
+        .send()
+        .await;
+    response
+        .assert_status(StatusCode::OK)
+        .assert_header("content-type", "text/event-stream");
+    let events = sse_events(response.text());
+    let ids: Vec<&str> = events.iter().map(|(id, _)| id.as_str()).collect();
+    assert_eq!(ids, ["0", "1", "2"]);
+    let values: Vec<Value> = events
+        .iter()
+        .map(|(_, data)| data["value"].clone())
+        .collect();
+    assert_eq!(values, expected);
+    assert_eq!(events[2].1["index"], 2);
+
+    client
+        .get("/random/int/events?start=0&end=100&interval_ms=5")
+        .send()
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "invalid_parameter");
+}
+
+#[tokio::test]
+async fn event_stream_without_count_keeps_going() {
+    use futures::StreamExt;
+
+    let response = client()
+        .get("/random/float/events?start=0&end=1&interval_ms=10")
+        .send_streaming()
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let mut body = response.into_body().into_data_stream();
+    let mut text = String::new();
+    let events = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let events = sse_events(&text);
+            if events.len() >= 5 {
+                return events;
+            }
+            let chunk = body.next().await.expect("stream ended early").unwrap();
+            text.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    })
+    .await
+    .expect("five events within five seconds");
+    for (index, (id, data)) in events.iter().enumerate() {
+        assert_eq!(id, &index.to_string());
+        let value = data["value"].as_f64().unwrap();
+        assert!((0.0..1.0).contains(&value));
+    }
+}
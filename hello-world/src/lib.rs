@@ -0,0 +1,10 @@
+pub mod tls;
+
+use axum::{response::Html, Router, routing::get};
+
+pub fn app() -> Router {
+    Router::new().route("/", get(handler))
+}
+async fn handler() -> Html<&'static str>{
+    Html("<h1>Hello Axum</h1>")
+}
\ No newline at end of file
@@ -1,17 +1,35 @@
-use axum::{http::StatusCode, Json, response::Html, Router, routing::get, routing::post};
-use serde::{Deserialize, Serialize};
-use tracing::info;
+use clap::Parser;
+use hello_world::tls::{self, TlsArgs};
+use server_core::{Server, ServerArgs};
+use std::process::ExitCode;
 
-#[tokio::main]
-async fn main() {
-    //init tracing
-    tracing_subscriber::fmt::init();
-    let app = Router::new().route("/", get(handler));
-    let listener
-        = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    info!("server listening on {}",listener.local_addr().unwrap());
-    axum::serve(listener, app).await.unwrap();
+#[derive(Parser)]
+struct Cli {
+    #[command(flatten)]
+    server: ServerArgs,
+    #[command(flatten)]
+    tls: TlsArgs,
 }
-async fn handler() -> Html<&'static str>{
-    Html("<h1>Hello Axum</h1>")
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let server = match Server::from_args(&cli.server) {
+        Ok(server) => server,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::from(2);
+        }
+    };
+    let tls = match cli.tls.load() {
+        Ok(tls) => tls,
+        Err(err) => {
+            tracing::error!(%err, "cannot load the TLS certificate");
+            return ExitCode::from(2);
+        }
+    };
+    match tls {
+        Some(config) => tls::serve(server, config, cli.tls.redirect_bind, hello_world::app()).await,
+        None => server.serve(hello_world::app()).await,
+    }
 }
\ No newline at end of file
@@ -0,0 +1,265 @@
+//! Optional HTTPS with rustls.
+//!
+//! With `--tls-cert` and `--tls-key` the server speaks TLS only, offering
+//! HTTP/2 and HTTP/1.1 through ALPN. The certificate and key are re-read
+//! when either file's modification time changes, so renewed certificates
+//! are picked up without a restart; new connections get the new
+//! certificate. `--redirect-bind` adds a plain-HTTP listener that answers
+//! every request with a permanent redirect to the HTTPS address.
+//!
+//! The HTTPS socket comes from [`Listener::bind`], so an activated TCP
+//! socket works too; Unix sockets are refused.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufReader},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Redirect, Response},
+    Router,
+};
+use axum_server::{tls_rustls::RustlsConfig, Handle};
+use clap::Args;
+use server_core::{
+    error::ErrorResponse, listener::Listener, shutdown::Shutdown, telemetry, Server,
+};
+
+/// How often the certificate files are checked for changes.
+pub const RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct TlsArgs {
+    /// PEM certificate chain; serve HTTPS instead of HTTP
+    #[arg(long, env = "TLS_CERT", requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1) for --tls-cert
+    #[arg(long, env = "TLS_KEY", requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+    /// Also listen for plain HTTP on this address and redirect to HTTPS
+    #[arg(long, env = "HTTP_REDIRECT_BIND", requires = "tls_cert")]
+    pub redirect_bind: Option<String>,
+}
+
+impl TlsArgs {
+    /// The configured certificate, or `None` when TLS is off.
+    pub fn load(&self) -> io::Result<Option<Tls>> {
+        match (&self.tls_cert, &self.tls_key) {
+            (Some(cert), Some(key)) => Tls::load(cert, key).map(Some),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// A certificate and key loaded from PEM files, shared between the
+/// acceptor and the reloader.
+#[derive(Clone)]
+pub struct Tls {
+    config: RustlsConfig,
+    cert: PathBuf,
+    key: PathBuf,
+    modified: Arc<Mutex<[Option<SystemTime>; 2]>>,
+}
+
+impl Tls {
+    pub fn load(cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> io::Result<Self> {
+        let (cert, key) = (cert.into(), key.into());
+        let modified = modified(&cert, &key);
+        let config = RustlsConfig::from_config(Arc::new(server_config(&cert, &key)?));
+        Ok(Tls {
+            config,
+            cert,
+            key,
+            modified: Arc::new(Mutex::new(modified)),
+        })
+    }
+
+    /// Re-read both files. On error the current certificate stays in use.
+    pub fn reload(&self) -> io::Result<()> {
+        let modified = modified(&self.cert, &self.key);
+        self.config
+            .reload_from_config(Arc::new(server_config(&self.cert, &self.key)?));
+        *self.modified.lock().unwrap() = modified;
+        Ok(())
+    }
+
+    /// Reload whenever either file's modification time changes, until
+    /// shutdown.
+    pub fn spawn_reloader(
+        &self,
+        every: Duration,
+        shutdown: &Shutdown,
+    ) -> tokio::task::JoinHandle<()> {
+        let tls = self.clone();
+        let token = shutdown.token();
+        shutdown.spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    () = token.cancelled() => break,
+                }
+                if modified(&tls.cert, &tls.key) == *tls.modified.lock().unwrap() {
+                    continue;
+                }
+                match tls.reload() {
+                    Ok(()) => {
+                        tracing::info!(cert = %tls.cert.display(), "reloaded TLS certificate")
+                    }
+                    Err(err) => {
+                        tracing::warn!(%err, cert = %tls.cert.display(), "failed to reload TLS certificate")
+                    }
+                }
+            }
+        })
+    }
+}
+
+fn modified(cert: &Path, key: &Path) -> [Option<SystemTime>; 2] {
+    [cert, key].map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+}
+
+fn server_config(cert: &Path, key: &Path) -> io::Result<rustls::ServerConfig> {
+    let invalid = |path: &Path, err: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {err}", path.display()),
+        )
+    };
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(cert)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    if certs.is_empty() {
+        return Err(invalid(cert, "no certificates found".into()));
+    }
+    let key = rustls_pemfile::private_key(&mut BufReader::new(File::open(key)?))?
+        .ok_or_else(|| invalid(key, "no private key found".into()))?;
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|err| invalid(cert, err.to_string()))?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|err| invalid(cert, err.to_string()))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+/// Serve `app` over TLS on `listener` until shutdown begins, then wait for
+/// open connections to finish. The caller bounds the wait, as
+/// [`Shutdown::drain`] does.
+pub async fn serve_https(
+    listener: std::net::TcpListener,
+    tls: &Tls,
+    app: Router,
+    shutdown: &Shutdown,
+) -> io::Result<()> {
+    let handle = Handle::new();
+    let token = shutdown.token();
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            token.cancelled().await;
+            handle.graceful_shutdown(None);
+        }
+    });
+    axum_server::from_tcp_rustls(listener, tls.config.clone())
+        .handle(handle)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+}
+
+/// Routes that send every request to the same path on `https_port`.
+pub fn redirect_app(https_port: u16) -> Router {
+    Router::new().fallback(redirect).with_state(https_port)
+}
+
+async fn redirect(State(https_port): State<u16>, headers: HeaderMap, uri: Uri) -> Response {
+    let Some(host) = headers.get(header::HOST).and_then(|v| v.to_str().ok()) else {
+        return ErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "bad_request",
+            "a Host header is required",
+        )
+        .into_response();
+    };
+    // Drop the port, leaving IPv6 literals such as `[::1]` intact.
+    let host = match host.rfind(':') {
+        Some(i) if !host[i..].contains(']') => &host[..i],
+        _ => host,
+    };
+    let authority = match https_port {
+        443 => host.to_string(),
+        port => format!("{host}:{port}"),
+    };
+    let path = uri.path_and_query().map_or("/", |p| p.as_str());
+    Redirect::permanent(&format!("https://{authority}{path}")).into_response()
+}
+
+/// Like [`Server::serve`], but over TLS, with the optional redirect
+/// listener and certificate reloading.
+pub async fn serve(
+    server: Server,
+    tls: Tls,
+    redirect_bind: Option<String>,
+    app: Router,
+) -> ExitCode {
+    let app = server.router(app);
+    let shutdown = server.shutdown();
+    let bind = &server.config().bind;
+    let listener = match Listener::bind(server.config()).await {
+        Ok(Listener::Tcp(listener)) => listener.into_std(),
+        #[cfg(unix)]
+        Ok(Listener::Unix(_)) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "TLS needs a TCP listener",
+        )),
+        Err(err) => Err(err),
+    };
+    let listener = match listener {
+        Ok(listener) => listener,
+        Err(err) => {
+            tracing::error!(%err, %bind, "cannot bind");
+            return ExitCode::FAILURE;
+        }
+    };
+    let addr = match listener.local_addr() {
+        Ok(addr) => addr,
+        Err(err) => {
+            tracing::error!(%err, %bind, "cannot read the bound address");
+            return ExitCode::FAILURE;
+        }
+    };
+    tracing::info!(%addr, "listening for HTTPS");
+
+    if let Some(redirect_bind) = redirect_bind {
+        let listener = match tokio::net::TcpListener::bind(&redirect_bind).await {
+            Ok(listener) => listener,
+            Err(err) => {
+                tracing::error!(%err, bind = %redirect_bind, "cannot bind");
+                return ExitCode::FAILURE;
+            }
+        };
+        tracing::info!(bind = %redirect_bind, "redirecting HTTP to HTTPS");
+        let redirects = axum::serve(listener, telemetry::instrument(redirect_app(addr.port())))
+            .with_graceful_shutdown(shutdown.cancelled());
+        shutdown.spawn(async move {
+            if let Err(err) = redirects.await {
+                tracing::error!(%err, "redirect listener failed");
+            }
+        });
+    }
+
+    tls.spawn_reloader(RELOAD_INTERVAL, shutdown);
+    shutdown.listen_for_signals();
+    shutdown
+        .drain(serve_https(listener, &tls, app, shutdown))
+        .await
+        .exit_code()
+}
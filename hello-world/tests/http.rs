@@ -0,0 +1,39 @@
+use axum::http::StatusCode;
+use server_core::{testing::TestClient, Server, ServerConfig};
+
+#[tokio::test]
+async fn index_page() {
+    TestClient::new(hello_world::app())
+        .get("/")
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .assert_html_snapshot("index");
+}
+
+#[tokio::test]
+async fn shared_endpoints() {
+    let server = Server::new(ServerConfig {
+        log_filter: "warn".to_string(),
+        ..ServerConfig::default()
+    });
+    let client = TestClient::new(server.router(hello_world::app()));
+
+    client
+        .get("/healthz")
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    client
+        .get("/readyz")
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+    let metrics = client.get("/metrics").send().await;
+    metrics.assert_status(StatusCode::OK);
+    assert!(metrics.text().contains("http_requests_total"));
+
+    let missing = client.get("/nope").send().await;
+    missing.assert_error(StatusCode::NOT_FOUND, "not_found");
+    assert!(missing.header("x-request-id").is_some());
+}
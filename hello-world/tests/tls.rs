@@ -0,0 +1,199 @@
+use std::{
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::Bytes,
+    http::{Request, StatusCode, Version},
+};
+use hello_world::tls::{self, Tls};
+use http_body_util::{BodyExt, Empty};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use rustls::pki_types::{CertificateDer, ServerName};
+use server_core::{shutdown::Shutdown, testing::TestClient};
+use tokio::net::TcpStream;
+use tokio_rustls::{client::TlsStream, TlsConnector};
+
+/// A directory unique to `test`, so tests can run in parallel.
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("hello-world-{test}-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Write a fresh self-signed `localhost` certificate and key into `dir` and
+/// return the certificate.
+fn write_cert(dir: &Path) -> CertificateDer<'static> {
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    fs::write(dir.join("cert.pem"), certified.cert.pem()).unwrap();
+    fs::write(dir.join("key.pem"), certified.key_pair.serialize_pem()).unwrap();
+    certified.cert.der().clone()
+}
+
+fn start(tls: &Tls, shutdown: &Shutdown) -> SocketAddr {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tls, shutdown) = (tls.clone(), shutdown.clone());
+    tokio::spawn(async move {
+        tls::serve_https(listener, &tls, hello_world::app(), &shutdown)
+            .await
+            .unwrap();
+    });
+    addr
+}
+
+async fn connect(
+    addr: SocketAddr,
+    trusted: &[CertificateDer<'static>],
+    alpn: &[&[u8]],
+) -> TlsStream<TcpStream> {
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in trusted {
+        roots.add(cert.clone()).unwrap();
+    }
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    config.alpn_protocols = alpn.iter().map(|p| p.to_vec()).collect();
+    let stream = TcpStream::connect(addr).await.unwrap();
+    TlsConnector::from(Arc::new(config))
+        .connect(ServerName::try_from("localhost").unwrap(), stream)
+        .await
+        .unwrap()
+}
+
+fn peer_certificate(stream: &TlsStream<TcpStream>) -> CertificateDer<'static> {
+    stream.get_ref().1.peer_certificates().unwrap()[0].clone()
+}
+
+#[tokio::test]
+async fn serves_http2_over_alpn() {
+    let dir = temp_dir("tls-h2");
+    let cert = write_cert(&dir);
+    let tls = Tls::load(dir.join("cert.pem"), dir.join("key.pem")).unwrap();
+    let shutdown = Shutdown::new(Duration::from_secs(1));
+    let addr = start(&tls, &shutdown);
+
+    let stream = connect(addr, &[cert], &[b"h2", b"http/1.1"]).await;
+    assert_eq!(stream.get_ref().1.alpn_protocol(), Some(&b"h2"[..]));
+    let (mut sender, connection) =
+        hyper::client::conn::http2::handshake(TokioExecutor::new(), TokioIo::new(stream))
+            .await
+            .unwrap();
+    tokio::spawn(connection);
+    let request = Request::get("https://localhost/")
+        .body(Empty::<Bytes>::new())
+        .unwrap();
+    let response = sender.send_request(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.version(), Version::HTTP_2);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], b"<h1>Hello Axum</h1>");
+
+    shutdown.trigger();
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn falls_back_to_http1() {
+    let dir = temp_dir("tls-h1");
+    let cert = write_cert(&dir);
+    let tls = Tls::load(dir.join("cert.pem"), dir.join("key.pem")).unwrap();
+    let shutdown = Shutdown::new(Duration::from_secs(1));
+    let addr = start(&tls, &shutdown);
+
+    let stream = connect(addr, &[cert], &[b"http/1.1"]).await;
+    assert_eq!(stream.get_ref().1.alpn_protocol(), Some(&b"http/1.1"[..]));
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+        .await
+        .unwrap();
+    tokio::spawn(connection);
+    let request = Request::get("/")
+        .header("host", "localhost")
+        .body(Empty::<Bytes>::new())
+        .unwrap();
+    let response = sender.send_request(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.version(), Version::HTTP_11);
+
+    shutdown.trigger();
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn reloads_changed_certificate() {
+    let dir = temp_dir("tls-reload");
+    let first = write_cert(&dir);
+    let tls = Tls::load(dir.join("cert.pem"), dir.join("key.pem")).unwrap();
+    let shutdown = Shutdown::new(Duration::from_secs(1));
+    let addr = start(&tls, &shutdown);
+    tls.spawn_reloader(Duration::from_millis(50), &shutdown);
+
+    let stream = connect(addr, std::slice::from_ref(&first), &[b"h2"]).await;
+    assert_eq!(peer_certificate(&stream), first);
+
+    // A half-written pair must not replace the working certificate.
+    fs::write(dir.join("key.pem"), "").unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let stream = connect(addr, std::slice::from_ref(&first), &[b"h2"]).await;
+    assert_eq!(peer_certificate(&stream), first);
+
+    let second = write_cert(&dir);
+    let trusted = [first, second.clone()];
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let stream = connect(addr, &trusted, &[b"h2"]).await;
+        if peer_certificate(&stream) == second {
+            break;
+        }
+        assert!(Instant::now() < deadline, "certificate was not reloaded");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    shutdown.trigger();
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn rejects_missing_key() {
+    let dir = temp_dir("tls-missing-key");
+    write_cert(&dir);
+    fs::write(dir.join("key.pem"), "").unwrap();
+    let err = Tls::load(dir.join("cert.pem"), dir.join("key.pem"))
+        .err()
+        .expect("an empty key file is rejected");
+    assert!(err.to_string().contains("no private key"), "{err}");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn redirects_http_to_https() {
+    let client = TestClient::new(tls::redirect_app(8443));
+    client
+        .get("/todos?done=true")
+        .header("host", "example.com:8080")
+        .send()
+        .await
+        .assert_status(StatusCode::PERMANENT_REDIRECT)
+        .assert_header("location", "https://example.com:8443/todos?done=true");
+    client
+        .get("/")
+        .header("host", "[::1]:80")
+        .send()
+        .await
+        .assert_header("location", "https://[::1]:8443/");
+
+    TestClient::new(tls::redirect_app(443))
+        .get("/")
+        .header("host", "example.com")
+        .send()
+        .await
+        .assert_header("location", "https://example.com/");
+}
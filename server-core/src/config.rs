@@ -0,0 +1,214 @@
+//! Server configuration, layered from lowest to highest precedence:
+//! built-in defaults, a TOML file, environment variables, command-line flags.
+//!
+//! | setting              | file key              | env var               | flag               |
+//! |----------------------|-----------------------|-----------------------|--------------------|
+//! | config file          |                       | `SERVER_CONFIG`       | `--config`         |
+//! | listen address       | `bind`                | `BIND_ADDR`           | `--bind`           |
+//! | socket permissions   | `socket_mode`         | `SOCKET_MODE`         | `--socket-mode`    |
+//! | log filter           | `log_filter`          | `RUST_LOG`            | `--log-filter`     |
+//! | log format           | `log_format`          | `LOG_FORMAT`          | `--log-format`     |
+//! | shutdown grace (s)   | `shutdown_grace_secs` | `SHUTDOWN_GRACE_SECS` | `--shutdown-grace` |
+//!
+//! The listen address is `host:port` or `unix:/path/to.sock`; see
+//! [`crate::listener`]. The socket mode is octal, e.g. `660`.
+
+use std::{fmt, path::PathBuf, time::Duration};
+
+use clap::{Args, ValueEnum};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per event
+    Json,
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub bind: String,
+    /// Permissions for a Unix socket file; `None` leaves them to the umask.
+    pub socket_mode: Option<u32>,
+    pub log_filter: String,
+    pub log_format: LogFormat,
+    pub shutdown_grace: Duration,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            bind: "0.0.0.0:3000".to_string(),
+            socket_mode: None,
+            log_filter: "info".to_string(),
+            log_format: LogFormat::Text,
+            shutdown_grace: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Command-line flags for [`ServerConfig`]; flatten them into an app's CLI.
+#[derive(Debug, Clone, Default, Args)]
+pub struct ServerArgs {
+    /// TOML file with server settings
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Address to listen on, `host:port` or `unix:/path/to.sock`
+    #[arg(long, value_name = "ADDR")]
+    pub bind: Option<String>,
+    /// Octal permissions for a Unix socket, e.g. `660`
+    #[arg(long, value_name = "MODE", value_parser = parse_mode)]
+    pub socket_mode: Option<u32>,
+    /// Log filter directives, e.g. `info,tower_http=debug`
+    #[arg(long, value_name = "FILTER")]
+    pub log_filter: Option<String>,
+    #[arg(long, value_enum)]
+    pub log_format: Option<LogFormat>,
+    /// Seconds in-flight requests get to finish after SIGINT or SIGTERM
+    #[arg(long, value_name = "SECS")]
+    pub shutdown_grace: Option<u64>,
+}
+
+/// The subset of settings a config file may contain.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    bind: Option<String>,
+    socket_mode: Option<String>,
+    log_filter: Option<String>,
+    log_format: Option<LogFormat>,
+    shutdown_grace_secs: Option<u64>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(PathBuf, String),
+    Env(&'static str, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, err) => write!(f, "cannot read {}: {err}", path.display()),
+            ConfigError::Parse(path, err) => write!(f, "invalid {}: {err}", path.display()),
+            ConfigError::Invalid(path, message) => write!(f, "invalid {}: {message}", path.display()),
+            ConfigError::Env(name, message) => write!(f, "{name}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl ServerConfig {
+    /// Resolve the configuration from every layer.
+    pub fn load(args: &ServerArgs) -> Result<Self, ConfigError> {
+        Self::load_from(args, |name| std::env::var(name).ok())
+    }
+
+    /// [`ServerConfig::load`] with an explicit environment lookup.
+    pub fn load_from(
+        args: &ServerArgs,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        let mut config = ServerConfig::default();
+
+        let path = args
+            .config
+            .clone()
+            .or_else(|| env("SERVER_CONFIG").map(PathBuf::from));
+        if let Some(path) = path {
+            let text = std::fs::read_to_string(&path)
+                .map_err(|err| ConfigError::Read(path.clone(), err))?;
+            let file: FileConfig =
+                toml::from_str(&text).map_err(|err| ConfigError::Parse(path.clone(), err))?;
+            let socket_mode = file
+                .socket_mode
+                .map(|mode| parse_mode(&mode))
+                .transpose()
+                .map_err(|err| ConfigError::Invalid(path.clone(), format!("socket_mode: {err}")))?;
+            config.apply(
+                file.bind,
+                socket_mode,
+                file.log_filter,
+                file.log_format,
+                file.shutdown_grace_secs,
+            );
+        }
+
+        let log_format = env("LOG_FORMAT")
+            .map(|value| {
+                LogFormat::from_str(&value, true).map_err(|_| {
+                    ConfigError::Env("LOG_FORMAT", format!("`{value}` is not text or json"))
+                })
+            })
+            .transpose()?;
+        let grace = env("SHUTDOWN_GRACE_SECS")
+            .map(|value| {
+                value.parse().map_err(|_| {
+                    ConfigError::Env(
+                        "SHUTDOWN_GRACE_SECS",
+                        format!("`{value}` is not a number of seconds"),
+                    )
+                })
+            })
+            .transpose()?;
+        let socket_mode = env("SOCKET_MODE")
+            .map(|value| parse_mode(&value).map_err(|err| ConfigError::Env("SOCKET_MODE", err)))
+            .transpose()?;
+        config.apply(
+            env("BIND_ADDR"),
+            socket_mode,
+            env("RUST_LOG"),
+            log_format,
+            grace,
+        );
+
+        config.apply(
+            args.bind.clone(),
+            args.socket_mode,
+            args.log_filter.clone(),
+            args.log_format,
+            args.shutdown_grace,
+        );
+        Ok(config)
+    }
+
+    fn apply(
+        &mut self,
+        bind: Option<String>,
+        socket_mode: Option<u32>,
+        log_filter: Option<String>,
+        log_format: Option<LogFormat>,
+        shutdown_grace_secs: Option<u64>,
+    ) {
+        if let Some(bind) = bind {
+            self.bind = bind;
+        }
+        if let Some(mode) = socket_mode {
+            self.socket_mode = Some(mode);
+        }
+        if let Some(log_filter) = log_filter {
+            self.log_filter = log_filter;
+        }
+        if let Some(log_format) = log_format {
+            self.log_format = log_format;
+        }
+        if let Some(secs) = shutdown_grace_secs {
+            self.shutdown_grace = Duration::from_secs(secs);
+        }
+    }
+}
+
+/// Parse octal file permissions such as `660` or `0o660`.
+fn parse_mode(value: &str) -> Result<u32, String> {
+    let digits = value.strip_prefix("0o").unwrap_or(value);
+    u32::from_str_radix(digits, 8)
+        .ok()
+        .filter(|&mode| mode <= 0o7777)
+        .ok_or_else(|| format!("`{value}` is not an octal file mode"))
+}
@@ -0,0 +1,93 @@
+//! The JSON error body every server in the workspace responds with:
+//! `{"error": "<code>", "message": "<text>", ...details}`.
+
+use std::{any::Any, borrow::Cow};
+
+use axum::{
+    http::{StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone)]
+pub struct ErrorResponse {
+    status: StatusCode,
+    error: Cow<'static, str>,
+    message: String,
+    details: Map<String, Value>,
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    error: &'a str,
+    message: &'a str,
+    #[serde(flatten)]
+    details: &'a Map<String, Value>,
+}
+
+impl ErrorResponse {
+    pub fn new(
+        status: StatusCode,
+        error: impl Into<Cow<'static, str>>,
+        message: impl Into<String>,
+    ) -> Self {
+        ErrorResponse {
+            status,
+            error: error.into(),
+            message: message.into(),
+            details: Map::new(),
+        }
+    }
+
+    /// Add an extra top-level field to the body.
+    pub fn with_detail(mut self, key: &str, value: impl Serialize) -> Self {
+        let value = serde_json::to_value(value).expect("error details serialize to JSON");
+        self.details.insert(key.to_string(), value);
+        self
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn internal() -> Self {
+        ErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal_error",
+            "internal server error",
+        )
+    }
+}
+
+impl IntoResponse for ErrorResponse {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            error: &self.error,
+            message: &self.message,
+            details: &self.details,
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
+
+/// Fallback for paths no route matches.
+pub async fn not_found(uri: Uri) -> ErrorResponse {
+    ErrorResponse::new(
+        StatusCode::NOT_FOUND,
+        "not_found",
+        format!("no route for {}", uri.path()),
+    )
+}
+
+/// Turns a handler panic into a 500 instead of dropping the connection.
+pub fn panic_response(panic: Box<dyn Any + Send + 'static>) -> Response {
+    let message = panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_default();
+    tracing::error!(panic = %message, "handler panicked");
+    ErrorResponse::internal().into_response()
+}
@@ -0,0 +1,103 @@
+//! `/healthz` and `/readyz` endpoints.
+//!
+//! `/healthz` answers as long as the process can serve requests at all.
+//! `/readyz` runs every registered [`Readiness`] check concurrently and
+//! returns `503` if any of them fails or takes longer than
+//! [`CHECK_TIMEOUT`].
+
+use std::{future::Future, sync::Arc, time::Duration};
+
+use axum::{http::StatusCode, routing::get, Json, Router};
+use futures::future::{self, BoxFuture, FutureExt};
+use serde::Serialize;
+
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+type Check = Arc<dyn Fn() -> BoxFuture<'static, Result<(), String>> + Send + Sync>;
+
+/// The set of checks `/readyz` runs.
+#[derive(Clone, Default)]
+pub struct Readiness {
+    checks: Vec<(String, Check)>,
+}
+
+impl Readiness {
+    pub fn new() -> Self {
+        Readiness::default()
+    }
+
+    /// Add a check; it is ready when the future resolves to `Ok(())`.
+    pub fn with_check<F, Fut>(mut self, name: impl Into<String>, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        self.checks
+            .push((name.into(), Arc::new(move || check().boxed())));
+        self
+    }
+
+    pub async fn run(&self) -> ReadinessReport {
+        let results = future::join_all(self.checks.iter().map(|(name, check)| async move {
+            let error = match tokio::time::timeout(CHECK_TIMEOUT, check()).await {
+                Ok(Ok(())) => None,
+                Ok(Err(err)) => Some(err),
+                Err(_) => Some(format!("timed out after {}s", CHECK_TIMEOUT.as_secs())),
+            };
+            CheckResult {
+                name: name.clone(),
+                ok: error.is_none(),
+                error,
+            }
+        }))
+        .await;
+        ReadinessReport {
+            ready: results.iter().all(|r| r.ok),
+            checks: results,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    pub checks: Vec<CheckResult>,
+}
+
+#[derive(Serialize)]
+struct Health {
+    status: &'static str,
+}
+
+/// Routes for `/healthz` and `/readyz`. Merge them after any rate limiting or
+/// authentication layers so probes are never rejected.
+pub fn routes<S>(readiness: Readiness) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/healthz", get(|| async { Json(Health { status: "ok" }) }))
+        .route(
+            "/readyz",
+            get(move || {
+                let readiness = readiness.clone();
+                async move {
+                    let report = readiness.run().await;
+                    let status = if report.ready {
+                        StatusCode::OK
+                    } else {
+                        StatusCode::SERVICE_UNAVAILABLE
+                    };
+                    (status, Json(report))
+                }
+            }),
+        )
+}
@@ -0,0 +1,17 @@
+//! Shared runtime for the axum servers in this workspace: configuration,
+//! logging, health and metrics endpoints, error bodies and graceful shutdown,
+//! tied together by [`server::Server`].
+
+pub mod config;
+pub mod error;
+pub mod health;
+pub mod listener;
+pub mod metrics;
+pub mod server;
+pub mod shutdown;
+pub mod telemetry;
+#[cfg(feature = "testing")]
+pub mod testing;
+
+pub use config::{ServerArgs, ServerConfig};
+pub use server::Server;
@@ -0,0 +1,282 @@
+//! Where the server accepts connections.
+//!
+//! - `host:port`: a TCP socket.
+//! - `unix:/path/to.sock`: a Unix domain socket. A socket file left behind by
+//!   a server that is no longer running is removed first; anything else at
+//!   the path is an error. The file is created with the configured
+//!   `socket_mode` and is left in place at exit, so a process that inherited
+//!   the socket keeps serving on it.
+//! - Socket activation: when `LISTEN_FDS` is set (and `LISTEN_PID`, if set,
+//!   is this process) the listening socket at fd 3 is adopted instead, as
+//!   systemd passes it. Either kind of socket works there. The `LISTEN_*`
+//!   variables are then removed so processes we spawn do not see them.
+//!
+//! Requests over a Unix socket carry no `ConnectInfo<SocketAddr>`, so
+//! per-address limits and allowlists see no peer address for them.
+
+use std::{fmt, io};
+
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+
+use crate::config::ServerConfig;
+
+#[cfg(unix)]
+pub(crate) use unix::serve as serve_unix;
+
+/// The first file descriptor passed by socket activation.
+#[cfg(unix)]
+pub const LISTEN_FDS_START: std::os::fd::RawFd = 3;
+
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl Listener {
+    /// Adopt an activated socket if there is one, otherwise bind
+    /// `config.bind`.
+    pub async fn bind(config: &ServerConfig) -> io::Result<Self> {
+        #[cfg(unix)]
+        {
+            if let Some(listener) = Listener::from_env()? {
+                tracing::info!(addr = %listener, "adopted socket from LISTEN_FDS");
+                return Ok(listener);
+            }
+            if let Some(path) = config.bind.strip_prefix("unix:") {
+                return unix::bind(path.as_ref(), config.socket_mode).map(Listener::Unix);
+            }
+        }
+        Ok(Listener::Tcp(TcpListener::bind(&config.bind).await?))
+    }
+
+    /// The socket passed through `LISTEN_FDS`, if any. Only the first call
+    /// can adopt it.
+    #[cfg(unix)]
+    pub fn from_env() -> io::Result<Option<Self>> {
+        unix::from_env()
+    }
+}
+
+impl fmt::Display for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Listener::Tcp(listener) => match listener.local_addr() {
+                Ok(addr) => write!(f, "{addr}"),
+                Err(_) => f.write_str("tcp"),
+            },
+            #[cfg(unix)]
+            Listener::Unix(listener) => {
+                match listener
+                    .local_addr()
+                    .ok()
+                    .and_then(|addr| addr.as_pathname().map(|path| path.display().to_string()))
+                {
+                    Some(path) => write!(f, "unix:{path}"),
+                    None => f.write_str("unix:<unnamed>"),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::{
+        fs, io,
+        os::{
+            fd::{BorrowedFd, FromRawFd, OwnedFd},
+            unix::fs::{FileTypeExt, PermissionsExt},
+        },
+        path::Path,
+        sync::atomic::{AtomicBool, Ordering},
+        time::Duration,
+    };
+
+    use axum::Router;
+    use hyper_util::{
+        rt::{TokioExecutor, TokioIo},
+        server::conn::auto::Builder,
+        service::TowerToHyperService,
+    };
+    use socket2::{SockRef, Socket, Type};
+    use tokio::net::{TcpListener, UnixListener};
+    use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+    use super::{Listener, LISTEN_FDS_START};
+
+    static ADOPTED: AtomicBool = AtomicBool::new(false);
+
+    pub(super) fn from_env() -> io::Result<Option<Listener>> {
+        let Ok(count) = std::env::var("LISTEN_FDS") else {
+            return Ok(None);
+        };
+        if let Ok(pid) = std::env::var("LISTEN_PID") {
+            if pid.parse() != Ok(std::process::id()) {
+                return Ok(None);
+            }
+        }
+        let count: u32 = count.parse().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("LISTEN_FDS=`{count}` is not a number"),
+            )
+        })?;
+        if count == 0 || ADOPTED.swap(true, Ordering::SeqCst) {
+            return Ok(None);
+        }
+        for var in ["LISTEN_FDS", "LISTEN_PID", "LISTEN_FDNAMES"] {
+            std::env::remove_var(var);
+        }
+        if count > 1 {
+            tracing::warn!(
+                count,
+                "LISTEN_FDS passed several sockets; using only the first"
+            );
+        }
+        // SAFETY: fd 3 is only inspected while borrowed. If it turns out not
+        // to be a listening socket it is left open, since then something else
+        // in this process may own it.
+        let borrowed = unsafe { BorrowedFd::borrow_raw(LISTEN_FDS_START) };
+        let socket = SockRef::from(&borrowed);
+        let not_listening = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("fd {LISTEN_FDS_START} from LISTEN_FDS is not a listening stream socket"),
+            )
+        };
+        if socket.r#type().map_err(|_| not_listening())? != Type::STREAM {
+            return Err(not_listening());
+        }
+        #[cfg(target_os = "linux")]
+        if !socket.is_listener()? {
+            return Err(not_listening());
+        }
+        let addr = socket.local_addr()?;
+        if !addr.is_unix() && addr.as_socket().is_none() {
+            return Err(not_listening());
+        }
+        // SAFETY: under the activation protocol the socket at fd 3 was
+        // passed to this process and nothing else owns it; `ADOPTED` makes
+        // sure it is wrapped only once.
+        let socket = Socket::from(unsafe { OwnedFd::from_raw_fd(LISTEN_FDS_START) });
+        // Keep it from leaking into processes we spawn.
+        socket.set_cloexec(true)?;
+        socket.set_nonblocking(true)?;
+        let fd = OwnedFd::from(socket);
+        if addr.is_unix() {
+            let listener = std::os::unix::net::UnixListener::from(fd);
+            Ok(Some(Listener::Unix(UnixListener::from_std(listener)?)))
+        } else {
+            let listener = std::net::TcpListener::from(fd);
+            Ok(Some(Listener::Tcp(TcpListener::from_std(listener)?)))
+        }
+    }
+
+    pub(super) fn bind(path: &Path, mode: Option<u32>) -> io::Result<UnixListener> {
+        remove_stale(path)?;
+        let Some(mode) = mode else {
+            return UnixListener::bind(path);
+        };
+        // Create the file with no more than `mode` allows, so it is never
+        // reachable with looser permissions. The umask is process-wide; files
+        // other threads create meanwhile only end up more restrictive.
+        let listener = {
+            let _umask = Umask::set(!mode & 0o777);
+            UnixListener::bind(path)?
+        };
+        // The umask cannot add bits such as setgid.
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+        Ok(listener)
+    }
+
+    /// Restores the previous umask when dropped.
+    struct Umask(libc::mode_t);
+
+    impl Umask {
+        fn set(mask: u32) -> Self {
+            // SAFETY: umask cannot fail and touches no memory.
+            Umask(unsafe { libc::umask(mask as libc::mode_t) })
+        }
+    }
+
+    impl Drop for Umask {
+        fn drop(&mut self) {
+            // SAFETY: as above.
+            unsafe { libc::umask(self.0) };
+        }
+    }
+
+    /// Remove a socket file nobody is listening on any more.
+    fn remove_stale(path: &Path) -> io::Result<()> {
+        let metadata = match fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        if !metadata.file_type().is_socket() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", path.display()),
+            ));
+        }
+        match std::os::unix::net::UnixStream::connect(path) {
+            Ok(_) => Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("another server is listening on {}", path.display()),
+            )),
+            Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => {
+                tracing::info!(path = %path.display(), "removing stale socket");
+                fs::remove_file(path)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Serve `app` on `listener` until `token` is cancelled, then wait for
+    /// open connections to finish. `axum::serve` only takes TCP listeners.
+    pub async fn serve(
+        listener: UnixListener,
+        app: Router,
+        token: CancellationToken,
+    ) -> io::Result<()> {
+        let connections = TaskTracker::new();
+        loop {
+            let stream = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(err) => {
+                        // Usually out of file descriptors; back off rather than spin.
+                        tracing::warn!(%err, "failed to accept a connection");
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        continue;
+                    }
+                },
+                () = token.cancelled() => break,
+            };
+            let service = TowerToHyperService::new(app.clone());
+            let token = token.clone();
+            connections.spawn(async move {
+                let builder = Builder::new(TokioExecutor::new());
+                let connection =
+                    builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+                tokio::pin!(connection);
+                let result = tokio::select! {
+                    result = connection.as_mut() => result,
+                    () = token.cancelled() => {
+                        connection.as_mut().graceful_shutdown();
+                        connection.await
+                    }
+                };
+                if let Err(err) = result {
+                    tracing::debug!(%err, "connection closed with an error");
+                }
+            });
+        }
+        connections.close();
+        connections.wait().await;
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+//! Prometheus metrics for HTTP traffic.
+//!
+//! [`Metrics::track`] counts requests and records their latency by method,
+//! route and status, and tracks the number of requests in flight.
+//! [`Metrics::routes`] serves everything at `/metrics` in the Prometheus text
+//! format. The route
+//! label is the matched route pattern (such as `/todos/:id`), so it stays
+//! low-cardinality; requests that match no route are labelled `unmatched`.
+
+use std::time::Instant;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+
+const LABELS: &[&str] = &["method", "route", "status"];
+
+/// Request metrics with their own registry, so several servers (or tests) in
+/// one process do not share counters.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    latency: HistogramVec,
+    in_flight: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests handled"),
+            LABELS,
+        )
+        .expect("valid metric");
+        let latency = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time from receiving a request to producing its response",
+            ),
+            LABELS,
+        )
+        .expect("valid metric");
+        let in_flight = IntGauge::new("http_requests_in_flight", "HTTP requests being handled")
+            .expect("valid metric");
+        registry
+            .register(Box::new(requests.clone()))
+            .expect("metric registered once");
+        registry
+            .register(Box::new(latency.clone()))
+            .expect("metric registered once");
+        registry
+            .register(Box::new(in_flight.clone()))
+            .expect("metric registered once");
+        Metrics {
+            registry,
+            requests,
+            latency,
+            in_flight,
+        }
+    }
+
+    /// The registry, for applications that want to add their own metrics.
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// The `/metrics` route.
+    pub fn routes<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let metrics = self.clone();
+        Router::new().route("/metrics", get(move || render(metrics.clone())))
+    }
+
+    /// Record every request `router` handles. Apply it last so that requests
+    /// rejected by other middleware are counted too.
+    pub fn track<S>(&self, router: Router<S>) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        router.layer(middleware::from_fn_with_state(self.clone(), track))
+    }
+
+    /// The registry in the Prometheus text exposition format.
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("text encoding never fails");
+        String::from_utf8(buffer).expect("text encoding is UTF-8")
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
+
+/// Decrements the in-flight gauge even if the request future is dropped.
+struct InFlight(IntGauge);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+async fn track(State(metrics): State<Metrics>, request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", MatchedPath::as_str)
+        .to_string();
+    metrics.in_flight.inc();
+    let _guard = InFlight(metrics.in_flight.clone());
+    let start = Instant::now();
+
+    let response = next.run(request).await;
+
+    let status = response.status().as_u16().to_string();
+    let labels = [method.as_str(), route.as_str(), status.as_str()];
+    metrics.requests.with_label_values(&labels).inc();
+    metrics
+        .latency
+        .with_label_values(&labels)
+        .observe(start.elapsed().as_secs_f64());
+    response
+}
+
+async fn render(metrics: Metrics) -> Response {
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, TextEncoder::new().format_type())],
+        metrics.encode(),
+    )
+        .into_response()
+}
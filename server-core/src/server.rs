@@ -0,0 +1,119 @@
+//! [`Server`] wires an application's `Router` into the shared runtime:
+//! logging, health and metrics endpoints, a JSON 404 fallback, panic
+//! recovery, request tracing, binding (TCP, Unix socket or socket
+//! activation), and graceful shutdown.
+
+use std::{net::SocketAddr, process::ExitCode};
+
+use axum::Router;
+use tower_http::catch_panic::CatchPanicLayer;
+
+use crate::{
+    config::{ConfigError, ServerArgs, ServerConfig},
+    error,
+    health::{self, Readiness},
+    listener::{self, Listener},
+    metrics::Metrics,
+    shutdown::Shutdown,
+    telemetry,
+};
+
+pub struct Server {
+    config: ServerConfig,
+    shutdown: Shutdown,
+    metrics: Metrics,
+    readiness: Readiness,
+    metrics_route: bool,
+}
+
+impl Server {
+    /// Install logging from `config`; do this before anything else logs.
+    pub fn new(config: ServerConfig) -> Self {
+        telemetry::init(&config);
+        Server {
+            shutdown: Shutdown::new(config.shutdown_grace),
+            config,
+            metrics: Metrics::new(),
+            readiness: Readiness::new(),
+            metrics_route: true,
+        }
+    }
+
+    /// [`ServerConfig::load`] followed by [`Server::new`].
+    pub fn from_args(args: &ServerArgs) -> Result<Self, ConfigError> {
+        Ok(Server::new(ServerConfig::load(args)?))
+    }
+
+    pub fn config(&self) -> &ServerConfig {
+        &self.config
+    }
+
+    /// Give this to background tasks so they stop at shutdown.
+    pub fn shutdown(&self) -> &Shutdown {
+        &self.shutdown
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Checks run by `/readyz`.
+    pub fn readiness(mut self, readiness: Readiness) -> Self {
+        self.readiness = readiness;
+        self
+    }
+
+    /// Do not mount `/metrics`; the app mounts [`Metrics::routes`] itself,
+    /// for example behind its own authentication.
+    pub fn without_metrics_route(mut self) -> Self {
+        self.metrics_route = false;
+        self
+    }
+
+    /// `app` with the shared endpoints and middleware, as [`Server::serve`]
+    /// runs it. The health and metrics routes are added outside any layers
+    /// `app` already has.
+    pub fn router(&self, app: Router) -> Router {
+        let mut app = app
+            .merge(health::routes(self.readiness.clone()))
+            .fallback(error::not_found);
+        if self.metrics_route {
+            app = app.merge(self.metrics.routes());
+        }
+        let app = app.layer(CatchPanicLayer::custom(error::panic_response));
+        telemetry::instrument(self.metrics.track(app))
+    }
+
+    /// Bind (see [`Listener::bind`]), serve `app` until a shutdown signal,
+    /// and drain. The exit code follows
+    /// [`crate::shutdown::Outcome::exit_code`], or is `1` when the address
+    /// cannot be bound.
+    pub async fn serve(self, app: Router) -> ExitCode {
+        let app = self.router(app);
+        let listener = match Listener::bind(&self.config).await {
+            Ok(listener) => listener,
+            Err(err) => {
+                tracing::error!(%err, bind = %self.config.bind, "cannot bind");
+                return ExitCode::FAILURE;
+            }
+        };
+        tracing::info!(addr = %listener, "listening");
+        self.shutdown.listen_for_signals();
+        let outcome = match listener {
+            Listener::Tcp(listener) => {
+                let server = axum::serve(
+                    listener,
+                    app.into_make_service_with_connect_info::<SocketAddr>(),
+                )
+                .with_graceful_shutdown(self.shutdown.cancelled());
+                self.shutdown.drain(server).await
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener) => {
+                let server = listener::serve_unix(listener, app, self.shutdown.token());
+                self.shutdown.drain(server).await
+            }
+        };
+        outcome.exit_code()
+    }
+}
@@ -0,0 +1,160 @@
+//! Graceful shutdown on SIGINT/SIGTERM.
+//!
+//! On the first signal the [`Shutdown`] token is cancelled: the server stops
+//! accepting connections and background tasks spawned through
+//! [`Shutdown::spawn`] are told to finish. In-flight requests get up to the
+//! grace period to complete. A second signal exits immediately.
+//!
+//! Exit codes: `0` after a clean drain, `1` if the server failed, and `124`
+//! (as with `timeout(1)`) when the grace period ran out with requests still
+//! open.
+
+use std::{future::IntoFuture, io, process::ExitCode, time::Duration};
+
+use tokio_util::{
+    sync::{CancellationToken, WaitForCancellationFutureOwned},
+    task::TaskTracker,
+};
+
+pub const DEFAULT_GRACE: Duration = Duration::from_secs(30);
+/// How long background tasks get to finish once the server has stopped.
+const TASK_GRACE: Duration = Duration::from_secs(5);
+
+/// How serving ended.
+#[derive(Debug)]
+pub enum Outcome {
+    Drained,
+    DeadlineExceeded,
+    Failed(io::Error),
+}
+
+impl Outcome {
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            Outcome::Drained => ExitCode::SUCCESS,
+            Outcome::DeadlineExceeded => ExitCode::from(124),
+            Outcome::Failed(_) => ExitCode::FAILURE,
+        }
+    }
+}
+
+/// Shutdown coordination shared by the server and its background tasks.
+#[derive(Clone)]
+pub struct Shutdown {
+    token: CancellationToken,
+    tasks: TaskTracker,
+    grace: Duration,
+}
+
+impl Shutdown {
+    pub fn new(grace: Duration) -> Self {
+        Shutdown {
+            token: CancellationToken::new(),
+            tasks: TaskTracker::new(),
+            grace,
+        }
+    }
+
+    /// Cancelled once shutdown begins.
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// Resolves once shutdown begins; pass it to `with_graceful_shutdown`.
+    pub fn cancelled(&self) -> WaitForCancellationFutureOwned {
+        self.token.clone().cancelled_owned()
+    }
+
+    /// Begin shutting down without a signal.
+    pub fn trigger(&self) {
+        self.token.cancel();
+    }
+
+    /// Spawn a background task that [`Shutdown::drain`] waits for. The task
+    /// should watch [`Shutdown::token`] and return when it is cancelled.
+    pub fn spawn<F>(&self, task: F) -> tokio::task::JoinHandle<F::Output>
+    where
+        F: std::future::Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.tasks.spawn(task)
+    }
+
+    /// Trigger shutdown on SIGINT or SIGTERM; a second signal exits at once.
+    pub fn listen_for_signals(&self) {
+        let token = self.token.clone();
+        let grace = self.grace;
+        tokio::spawn(async move {
+            let name = signal().await;
+            tracing::info!(signal = name, grace_secs = grace.as_secs(), "shutting down");
+            token.cancel();
+            let name = signal().await;
+            tracing::warn!(signal = name, "second signal, exiting immediately");
+            std::process::exit(130);
+        });
+    }
+
+    /// Run `server` until it has drained after shutdown began, or until the
+    /// grace period runs out, then wait briefly for background tasks.
+    pub async fn drain<F>(&self, server: F) -> Outcome
+    where
+        F: IntoFuture<Output = io::Result<()>>,
+    {
+        let deadline = async {
+            self.token.cancelled().await;
+            tokio::time::sleep(self.grace).await;
+        };
+        let outcome = tokio::select! {
+            result = server.into_future() => match result {
+                Ok(()) => Outcome::Drained,
+                Err(err) => Outcome::Failed(err),
+            },
+            () = deadline => Outcome::DeadlineExceeded,
+        };
+        // The server can also stop on its own; background tasks still need
+        // to hear about it.
+        self.token.cancel();
+        self.tasks.close();
+        if tokio::time::timeout(TASK_GRACE, self.tasks.wait())
+            .await
+            .is_err()
+        {
+            tracing::warn!("background tasks did not finish in time");
+        }
+        match &outcome {
+            Outcome::Drained => tracing::info!("all connections drained"),
+            Outcome::DeadlineExceeded => {
+                tracing::warn!("grace period expired with requests still in flight")
+            }
+            Outcome::Failed(err) => tracing::error!(%err, "server failed"),
+        }
+        outcome
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Shutdown::new(DEFAULT_GRACE)
+    }
+}
+
+/// Wait for SIGINT or SIGTERM and return its name.
+async fn signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate = signal(SignalKind::terminate()).expect("install SIGTERM handler");
+        let mut interrupt = signal(SignalKind::interrupt()).expect("install SIGINT handler");
+        tokio::select! {
+            _ = terminate.recv() => "SIGTERM",
+            _ = interrupt.recv() => "SIGINT",
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("install Ctrl-C handler");
+        "Ctrl-C"
+    }
+}
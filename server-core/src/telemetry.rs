@@ -0,0 +1,84 @@
+//! Logging setup and per-request tracing.
+//!
+//! The filter and format come from [`ServerConfig`]: `RUST_LOG` or
+//! `--log-filter` select what is logged, and `LOG_FORMAT=json` switches from
+//! human-readable lines to one JSON object per event. Every request runs in
+//! a `request` span carrying its method, path, `x-request-id`, and once it
+//! completes, the status and latency. A request id sent by the client is
+//! kept; otherwise a UUID is generated. Either way it is echoed back in the
+//! response.
+
+use std::time::Duration;
+
+use axum::{
+    extract::Request,
+    http::{HeaderName, Response},
+    Router,
+};
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, RequestId, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+use tracing::{field::Empty, Span};
+use tracing_subscriber::EnvFilter;
+
+use crate::config::{LogFormat, ServerConfig};
+
+const REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Install the global subscriber. Does nothing if one is already installed.
+pub fn init(config: &ServerConfig) {
+    let filter = EnvFilter::try_new(&config.log_filter).unwrap_or_else(|err| {
+        eprintln!("invalid log filter `{}`: {err}", config.log_filter);
+        EnvFilter::new("info")
+    });
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    let _ = match config.log_format {
+        LogFormat::Json => builder
+            .json()
+            .flatten_event(true)
+            .with_span_list(false)
+            .try_init(),
+        LogFormat::Text => builder.try_init(),
+    };
+}
+
+/// Wrap `router` with request-id handling and the per-request span.
+pub fn instrument<S>(router: Router<S>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    // Layers run outside-in in the reverse order they are added: the id is
+    // assigned first, so the span can record it.
+    router
+        .layer(PropagateRequestIdLayer::new(REQUEST_ID))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(make_span)
+                .on_request(())
+                .on_response(on_response),
+        )
+        .layer(SetRequestIdLayer::new(REQUEST_ID, MakeRequestUuid))
+}
+
+fn make_span(request: &Request) -> Span {
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+        .unwrap_or("");
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        path = %request.uri().path(),
+        request_id,
+        status = Empty,
+        latency_ms = Empty,
+    )
+}
+
+fn on_response<B>(response: &Response<B>, latency: Duration, span: &Span) {
+    span.record("status", response.status().as_u16());
+    span.record("latency_ms", latency.as_secs_f64() * 1000.0);
+    tracing::info!("finished processing request");
+}
@@ -0,0 +1,272 @@
+//! In-process request helpers for integration tests, enabled by the
+//! `testing` feature.
+//!
+//! [`TestClient`] drives a `Router` with `tower::ServiceExt::oneshot`, so no
+//! socket is bound. Requests carry a `ConnectInfo<SocketAddr>` as they would
+//! under [`crate::Server::serve`], defaulting to `127.0.0.1`.
+//!
+//! HTML bodies can be compared against golden files with
+//! [`TestResponse::assert_html_snapshot`]. Run the tests with
+//! `UPDATE_SNAPSHOTS=1` to write or refresh the files.
+
+use std::{
+    fs,
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
+};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::ConnectInfo,
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode},
+    response::Response,
+    Router,
+};
+use http_body_util::BodyExt;
+use serde::{de::DeserializeOwned, Serialize};
+use tower::ServiceExt;
+
+const DEFAULT_PEER: SocketAddr = SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+
+#[derive(Clone)]
+pub struct TestClient {
+    router: Router,
+}
+
+impl TestClient {
+    pub fn new(router: Router) -> Self {
+        TestClient { router }
+    }
+
+    pub fn request(&self, method: Method, uri: &str) -> TestRequest {
+        TestRequest {
+            router: self.router.clone(),
+            builder: Request::builder().method(method).uri(uri),
+            peer: DEFAULT_PEER,
+            body: Body::empty(),
+        }
+    }
+
+    pub fn get(&self, uri: &str) -> TestRequest {
+        self.request(Method::GET, uri)
+    }
+
+    pub fn post(&self, uri: &str) -> TestRequest {
+        self.request(Method::POST, uri)
+    }
+
+    pub fn patch(&self, uri: &str) -> TestRequest {
+        self.request(Method::PATCH, uri)
+    }
+
+    pub fn delete(&self, uri: &str) -> TestRequest {
+        self.request(Method::DELETE, uri)
+    }
+}
+
+#[must_use = "a request does nothing until it is sent"]
+pub struct TestRequest {
+    router: Router,
+    builder: axum::http::request::Builder,
+    peer: SocketAddr,
+    body: Body,
+}
+
+impl TestRequest {
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.builder = self.builder.header(name, value);
+        self
+    }
+
+    /// Serialize `value` as the body and set `Content-Type: application/json`.
+    pub fn json<T: Serialize>(mut self, value: &T) -> Self {
+        let body = serde_json::to_vec(value).expect("request body serializes to JSON");
+        self.builder = self
+            .builder
+            .header(header::CONTENT_TYPE, "application/json");
+        self.body = Body::from(body);
+        self
+    }
+
+    /// A raw body, sent as is.
+    pub fn body(mut self, body: impl Into<Body>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// The client address seen by the app.
+    pub fn peer(mut self, peer: SocketAddr) -> Self {
+        self.peer = peer;
+        self
+    }
+
+    pub async fn send(self) -> TestResponse {
+        let method = self.builder.method_ref().cloned().unwrap_or_default();
+        let uri = self.builder.uri_ref().cloned().unwrap_or_default();
+        let (parts, body) = self.send_streaming().await.into_parts();
+        let body = body
+            .collect()
+            .await
+            .expect("response body can be read")
+            .to_bytes();
+        TestResponse {
+            request: format!("{method} {uri}"),
+            status: parts.status,
+            headers: parts.headers,
+            body,
+        }
+    }
+
+    /// Send the request without buffering the body, for responses that do
+    /// not end on their own such as event streams.
+    pub async fn send_streaming(self) -> Response {
+        let request = self
+            .builder
+            .extension(ConnectInfo(self.peer))
+            .body(self.body)
+            .expect("valid test request");
+        self.router
+            .oneshot(request)
+            .await
+            .expect("routers are infallible")
+    }
+}
+
+/// A fully buffered response. Assertions panic with the request line and
+/// the body so a failing test shows what came back.
+pub struct TestResponse {
+    request: String,
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl TestResponse {
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// The header as a string, if present and valid UTF-8.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+
+    pub fn bytes(&self) -> &Bytes {
+        &self.body
+    }
+
+    pub fn text(&self) -> &str {
+        std::str::from_utf8(&self.body)
+            .unwrap_or_else(|err| panic!("{}: body is not UTF-8: {err}", self.request))
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> T {
+        serde_json::from_slice(&self.body).unwrap_or_else(|err| {
+            panic!(
+                "{}: body is not the expected JSON: {err}\n{}",
+                self.request,
+                String::from_utf8_lossy(&self.body)
+            )
+        })
+    }
+
+    #[track_caller]
+    pub fn assert_status(&self, expected: StatusCode) -> &Self {
+        assert_eq!(
+            self.status,
+            expected,
+            "{}: unexpected status; body:\n{}",
+            self.request,
+            String::from_utf8_lossy(&self.body)
+        );
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_header(&self, name: &str, expected: &str) -> &Self {
+        let name = HeaderName::try_from(name).expect("valid header name");
+        assert_eq!(
+            self.headers.get(&name),
+            Some(&HeaderValue::try_from(expected).expect("valid header value")),
+            "{}: unexpected `{name}` header",
+            self.request
+        );
+        self
+    }
+
+    /// Assert that an error body has the shared `{error, message}` shape
+    /// with the given `error` code.
+    #[track_caller]
+    pub fn assert_error(&self, status: StatusCode, code: &str) -> &Self {
+        self.assert_status(status);
+        let body: serde_json::Value = self.json();
+        assert_eq!(
+            body["error"], code,
+            "{}: unexpected error code",
+            self.request
+        );
+        assert!(
+            body["message"].is_string(),
+            "{}: error body has no message",
+            self.request
+        );
+        self
+    }
+
+    /// Compare the body with `tests/snapshots/<name>.html` in the crate under
+    /// test. Line endings and trailing whitespace are normalized first. With
+    /// `UPDATE_SNAPSHOTS=1` the file is written instead.
+    #[track_caller]
+    pub fn assert_html_snapshot(&self, name: &str) -> &Self {
+        let content_type = self.header(header::CONTENT_TYPE.as_str()).unwrap_or("");
+        assert!(
+            content_type.starts_with("text/html"),
+            "{}: expected an HTML response, got `{content_type}`",
+            self.request
+        );
+        let actual = normalize(self.text());
+        let path = snapshot_dir().join(format!("{name}.html"));
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some_and(|v| v == "1") {
+            fs::create_dir_all(path.parent().unwrap()).expect("create snapshot directory");
+            fs::write(&path, &actual).expect("write snapshot");
+            return self;
+        }
+        let expected = match fs::read_to_string(&path) {
+            Ok(expected) => normalize(&expected),
+            Err(err) => panic!(
+                "{}: cannot read snapshot {}: {err}\nrun with UPDATE_SNAPSHOTS=1 to create it",
+                self.request,
+                path.display()
+            ),
+        };
+        assert!(
+            actual == expected,
+            "{}: body differs from snapshot {}\nrun with UPDATE_SNAPSHOTS=1 to accept it\n--- expected\n{expected}\n--- actual\n{actual}",
+            self.request,
+            path.display()
+        );
+        self
+    }
+}
+
+/// Cargo sets `CARGO_MANIFEST_DIR` to the package under test when running
+/// its integration tests.
+fn snapshot_dir() -> PathBuf {
+    let root = std::env::var_os("CARGO_MANIFEST_DIR").expect("tests run under cargo");
+    PathBuf::from(root).join("tests").join("snapshots")
+}
+
+fn normalize(text: &str) -> String {
+    let mut out: String = text
+        .replace("\r\n", "\n")
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n");
+    out.push('\n');
+    out
+}
@@ -0,0 +1,166 @@
+#![cfg(unix)]
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+    net::TcpStream,
+    os::{fd::AsRawFd, unix::fs::PermissionsExt, unix::process::CommandExt},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use axum::{body::Bytes, http::Request, routing::get, Router};
+use http_body_util::{BodyExt, Empty};
+use hyper_util::rt::TokioIo;
+use server_core::{listener::Listener, Server, ServerConfig};
+use tokio::net::UnixStream;
+
+fn socket_path(test: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("server-core-{test}-{}.sock", std::process::id()))
+}
+
+fn config(path: &Path) -> ServerConfig {
+    ServerConfig {
+        bind: format!("unix:{}", path.display()),
+        socket_mode: Some(0o600),
+        log_filter: "warn".to_string(),
+        shutdown_grace: Duration::from_secs(1),
+        ..ServerConfig::default()
+    }
+}
+
+async fn fetch(path: &Path, uri: &str) -> (u16, String) {
+    let stream = UnixStream::connect(path).await.unwrap();
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+        .await
+        .unwrap();
+    tokio::spawn(connection);
+    let request = Request::get(uri)
+        .header("host", "localhost")
+        .body(Empty::<Bytes>::new())
+        .unwrap();
+    let response = sender.send_request(request).await.unwrap();
+    let status = response.status().as_u16();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn serves_on_a_unix_socket() {
+    let path = socket_path("serve");
+    // A socket file left by a server that is gone.
+    drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+    assert!(path.exists());
+
+    let server = Server::new(config(&path));
+    let shutdown = server.shutdown().clone();
+    let app = Router::new().route("/", get(|| async { "over a socket" }));
+    let serving = tokio::spawn(server.serve(app));
+
+    let mut attempts = 0;
+    while UnixStream::connect(&path).await.is_err() {
+        attempts += 1;
+        assert!(attempts < 100, "server did not start");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    assert_eq!(fetch(&path, "/").await, (200, "over a socket".to_string()));
+    assert_eq!(fetch(&path, "/healthz").await.0, 200);
+
+    // A second server must not take over a live socket.
+    let err = Listener::bind(&config(&path)).await.err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+
+    shutdown.trigger();
+    let code = tokio::time::timeout(Duration::from_secs(5), serving)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(code, std::process::ExitCode::SUCCESS);
+    fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn refuses_to_replace_other_files() {
+    let path = socket_path("regular-file");
+    fs::write(&path, "not a socket").unwrap();
+    let err = Listener::bind(&config(&path)).await.err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "not a socket");
+    fs::remove_file(path).unwrap();
+}
+
+/// Set in the child process `adopts_socket_from_listen_fds` starts.
+const ACTIVATED_CHILD: &str = "SERVER_CORE_TEST_ACTIVATED_CHILD";
+
+#[test]
+fn adopts_socket_from_listen_fds() {
+    if std::env::var_os(ACTIVATED_CHILD).is_some() {
+        return serve_activated();
+    }
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let fd = listener.as_raw_fd();
+    // Run this test again in a child that gets the socket the way systemd
+    // passes it.
+    let mut command = Command::new(std::env::current_exe().unwrap());
+    command
+        .args(["--exact", "adopts_socket_from_listen_fds", "--nocapture"])
+        .env(ACTIVATED_CHILD, "1")
+        .env("LISTEN_FDS", "1")
+        .env_remove("LISTEN_PID")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    // SAFETY: only async-signal-safe calls between fork and exec.
+    unsafe {
+        command.pre_exec(move || {
+            let result = if fd == 3 {
+                libc::fcntl(fd, libc::F_SETFD, 0)
+            } else {
+                libc::dup2(fd, 3)
+            };
+            if result < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let mut child = command.spawn().unwrap();
+    // Only the child can accept now.
+    drop(listener);
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    stream
+        .write_all(b"GET /listen-fds HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    let read = stream.read_to_string(&mut response);
+    child.kill().unwrap();
+    child.wait().unwrap();
+    read.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    // The variables are not passed on to processes the server starts.
+    assert!(response.ends_with("unset"), "{response}");
+}
+
+/// The child side: serve on whatever `LISTEN_FDS` passed. The configured
+/// address cannot be bound, so serving at all means the socket was adopted.
+fn serve_activated() {
+    let config = ServerConfig {
+        bind: "unix:/nonexistent/server-core.sock".to_string(),
+        log_filter: "warn".to_string(),
+        ..ServerConfig::default()
+    };
+    let app = Router::new().route(
+        "/listen-fds",
+        get(|| async { std::env::var("LISTEN_FDS").unwrap_or_else(|_| "unset".into()) }),
+    );
+    tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(Server::new(config).serve(app));
+}